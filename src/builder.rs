@@ -0,0 +1,262 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Builders for constructing tracklists programmatically.
+//!
+//! ```
+//! use cue_sheet::builder::{TrackBuilder, TrackFileBuilder, TracklistBuilder};
+//! use cue_sheet::parser::{FileFormat, Time, TrackType};
+//!
+//! let tracklist = TracklistBuilder::new()
+//!     .title("Session")
+//!     .add_file(
+//!         TrackFileBuilder::new("session.wav", FileFormat::Wave)
+//!             .add_track(TrackBuilder::new(1, TrackType::Audio).index(1, Time::new(0, 0, 0)))
+//!             .add_track(TrackBuilder::new(2, TrackType::Audio).index(1, Time::new(3, 0, 0))),
+//!     )
+//!     .build();
+//! assert_eq!(tracklist.tracks().count(), 2);
+//! ```
+
+use parser::{FileFormat, Time, TrackFlag, TrackType};
+use tracklist::{Track, TrackFile, Tracklist};
+
+/// Builder for a `Tracklist`.
+#[derive(Clone, Debug, Default)]
+pub struct TracklistBuilder {
+    tracklist: Tracklist,
+}
+
+impl TracklistBuilder {
+    /// Creates a builder for an empty tracklist.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the title of the album.
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.tracklist.title = Some(title.into());
+        self
+    }
+
+    /// Sets the performer of the album.
+    pub fn performer<S: Into<String>>(mut self, performer: S) -> Self {
+        self.tracklist.performer = Some(performer.into());
+        self
+    }
+
+    /// Sets the songwriter of the album.
+    pub fn songwriter<S: Into<String>>(mut self, songwriter: S) -> Self {
+        self.tracklist.songwriter = Some(songwriter.into());
+        self
+    }
+
+    /// Sets the UPC/EAN catalog number.
+    pub fn catalog<S: Into<String>>(mut self, catalog: S) -> Self {
+        self.tracklist.catalog = Some(catalog.into());
+        self
+    }
+
+    /// Sets the genre, written as `REM GENRE`.
+    pub fn genre<S: Into<String>>(mut self, genre: S) -> Self {
+        self.tracklist.genre = Some(genre.into());
+        self
+    }
+
+    /// Sets the date, written as `REM DATE`.
+    pub fn date<S: Into<String>>(mut self, date: S) -> Self {
+        self.tracklist.date = Some(date.into());
+        self
+    }
+
+    /// Appends a file to the tracklist.
+    pub fn add_file(mut self, file: TrackFileBuilder) -> Self {
+        self.tracklist.files.push(file.build());
+        self
+    }
+
+    /// Returns the finished tracklist.
+    pub fn build(self) -> Tracklist {
+        self.tracklist
+    }
+}
+
+/// Builder for a `TrackFile`.
+#[derive(Clone, Debug)]
+pub struct TrackFileBuilder {
+    file: TrackFile,
+}
+
+impl TrackFileBuilder {
+    /// Creates a builder for a file without tracks.
+    pub fn new<S: Into<String>>(name: S, format: FileFormat) -> Self {
+        TrackFileBuilder {
+            file: TrackFile {
+                tracks: Vec::new(),
+                name: name.into(),
+                format,
+            },
+        }
+    }
+
+    /// Appends a track to the file.
+    pub fn add_track(mut self, track: TrackBuilder) -> Self {
+        self.file.tracks.push(track.build());
+        self
+    }
+
+    /// Returns the finished file.
+    ///
+    /// Like when parsing, tracks without an explicit duration end where the next track's first
+    /// index starts, so only the duration of the last track stays unknown.
+    pub fn build(mut self) -> TrackFile {
+        for i in 1..self.file.tracks.len() {
+            let next = self.file.tracks[i]
+                .index
+                .first()
+                .map(|index| index.1.clone());
+            let track = &mut self.file.tracks[i - 1];
+            if track.duration.is_none() {
+                if let (Some(start), Some(next)) = (track.start(), next) {
+                    track.duration = next.checked_sub(&start);
+                }
+            }
+        }
+        self.file
+    }
+}
+
+/// Builder for a `Track`.
+#[derive(Clone, Debug)]
+pub struct TrackBuilder {
+    track: Track,
+}
+
+impl TrackBuilder {
+    /// Creates a builder for a track without any indices.
+    pub fn new(number: u32, track_type: TrackType) -> Self {
+        TrackBuilder {
+            track: Track {
+                title: None,
+                track_type,
+                duration: None,
+                index: Vec::new(),
+                number,
+                performer: None,
+                songwriter: None,
+                isrc: None,
+                date: None,
+                flags: Vec::new(),
+                postgap: None,
+            },
+        }
+    }
+
+    /// Sets the title of the track.
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.track.title = Some(title.into());
+        self
+    }
+
+    /// Sets the performer of the track.
+    pub fn performer<S: Into<String>>(mut self, performer: S) -> Self {
+        self.track.performer = Some(performer.into());
+        self
+    }
+
+    /// Sets the songwriter of the track.
+    pub fn songwriter<S: Into<String>>(mut self, songwriter: S) -> Self {
+        self.track.songwriter = Some(songwriter.into());
+        self
+    }
+
+    /// Sets the ISRC of the track, normalized to uppercase.
+    pub fn isrc<S: Into<String>>(mut self, isrc: S) -> Self {
+        self.track.isrc = Some(isrc.into().to_uppercase());
+        self
+    }
+
+    /// Adds an index, use number 1 for the start of the track and 0 for its pregap.
+    pub fn index(mut self, number: u32, time: Time) -> Self {
+        self.track.index.push((number, time));
+        self.track.index.sort_by_key(|index| index.0);
+        self
+    }
+
+    /// Adds a subcode flag.
+    pub fn flag(mut self, flag: TrackFlag) -> Self {
+        self.track.flags.push(flag);
+        self
+    }
+
+    /// Sets the duration of the track, which otherwise is derived from the next track.
+    pub fn duration(mut self, duration: Time) -> Self {
+        self.track.duration = Some(duration);
+        self
+    }
+
+    /// Sets the length of the silence after the track.
+    pub fn postgap(mut self, postgap: Time) -> Self {
+        self.track.postgap = Some(postgap);
+        self
+    }
+
+    /// Returns the finished track.
+    pub fn build(self) -> Track {
+        self.track
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_two_tracks() {
+        let tracklist = TracklistBuilder::new()
+            .performer("The Band")
+            .title("Live Session")
+            .catalog("0123456789012")
+            .add_file(
+                TrackFileBuilder::new("session.wav", FileFormat::Wave)
+                    .add_track(
+                        TrackBuilder::new(1, TrackType::Audio)
+                            .title("Opening")
+                            .index(1, Time::new(0, 0, 0)),
+                    )
+                    .add_track(
+                        TrackBuilder::new(2, TrackType::Audio)
+                            .title("Closing")
+                            .isrc("usabc1234567")
+                            .index(1, Time::new(4, 2, 10))
+                            .index(0, Time::new(4, 0, 0)),
+                    ),
+            )
+            .build();
+
+        let tracks: Vec<&Track> = tracklist.tracks().collect();
+        assert_eq!(tracks[0].duration, Some(Time::new(4, 0, 0)));
+        assert_eq!(tracks[1].index[0], (0, Time::new(4, 0, 0)));
+        assert_eq!(tracks[1].duration, None);
+
+        let cue = tracklist.to_cue_string();
+        assert!(cue.contains("FILE \"session.wav\" WAVE"));
+        assert!(cue.contains("    INDEX 01 04:02:10"));
+
+        let reparsed = Tracklist::parse_strict(&cue).unwrap();
+        assert_eq!(reparsed, tracklist);
+    }
+}
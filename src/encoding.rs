@@ -0,0 +1,75 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Decoding of cue sheets which are not necessarily encoded as UTF-8.
+
+/// Characters of Windows-1252 in the range `0x80..0xA0`, the rest matches ISO-8859-1.
+const WINDOWS_1252: [char; 32] = [
+    '\u{20ac}', '\u{81}', '\u{201a}', '\u{192}', '\u{201e}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2c6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8d}', '\u{17d}', '\u{8f}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201c}', '\u{201d}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2dc}', '\u{2122}', '\u{161}', '\u{203a}', '\u{153}', '\u{9d}', '\u{17e}', '\u{178}',
+];
+
+/// Returns the value of a `REM ENCODING` line, if the cue sheet contains one.
+pub(crate) fn encoding_hint(bytes: &[u8]) -> Option<String> {
+    bytes.split(|&b| b == b'\n').find_map(|line| {
+        let line = String::from_utf8_lossy(line);
+        let mut parts = line.trim().splitn(3, char::is_whitespace);
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(rem), Some(key), Some(value))
+                if rem.eq_ignore_ascii_case("REM") && key.eq_ignore_ascii_case("ENCODING") =>
+            {
+                Some(value.trim().trim_matches('"').to_string())
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Decodes `bytes` honoring the encoding named by `hint`.
+///
+/// Without a (known) hint the content is decoded as UTF-8 if it is valid UTF-8 and as
+/// Windows-1252 otherwise, which is what most older rippers produce.
+pub(crate) fn decode(bytes: &[u8], hint: Option<&str>) -> String {
+    let bytes = if bytes.starts_with(b"\xef\xbb\xbf") {
+        &bytes[3..]
+    } else {
+        bytes
+    };
+
+    match hint.map(|h| h.to_uppercase()).as_deref() {
+        Some("UTF-8") | Some("UTF8") => String::from_utf8_lossy(bytes).into_owned(),
+        Some("ISO-8859-1") | Some("ISO8859-1") | Some("LATIN1") | Some("LATIN-1") => {
+            bytes.iter().map(|&b| b as char).collect()
+        }
+        Some("WINDOWS-1252") | Some("CP1252") => decode_windows_1252(bytes),
+        _ => match ::std::str::from_utf8(bytes) {
+            Ok(s) => s.to_string(),
+            Err(_) => decode_windows_1252(bytes),
+        },
+    }
+}
+
+fn decode_windows_1252(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| match b {
+            0x80..=0x9f => WINDOWS_1252[(b - 0x80) as usize],
+            _ => b as char,
+        })
+        .collect()
+}
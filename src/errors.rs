@@ -16,20 +16,109 @@
 
 //! The errors used by this crate.
 //!
-//! Notice that so far error handling was done rather quickly with a lot of string based error
-//! messages.
+//! Many errors are still only described by a message, those are `Error::Msg`. Errors with a
+//! known location in the cue sheet carry its line number.
 
-error_chain! {
-    types {
-        Error, ErrorKind, ResultExt, Result;
+use std::error;
+use std::fmt;
+use std::io;
+use std::num::ParseIntError;
+use std::result;
+
+/// The error type of this crate.
+#[derive(Debug)]
+pub enum Error {
+    /// A line of the cue sheet couldn't be parsed.
+    Parse {
+        /// Line number, starting at 1.
+        line: usize,
+        /// Description of the problem.
+        message: String,
+    },
+
+    /// A command was found where it isn't allowed, or isn't known at all.
+    UnexpectedCommand {
+        /// Line number, starting at 1.
+        line: usize,
+        /// The command as it was parsed.
+        command: String,
+    },
+
+    /// A time wasn't of the form `mm:ss:ff` or one of its fields was out of range.
+    MalformedTime(String),
+
+    /// Reading a file failed.
+    Io(io::Error),
+
+    /// Parsing a string into an integer failed.
+    ParseInt(ParseIntError),
+
+    /// Any other error, described by its message.
+    Msg(String),
+}
+
+impl Error {
+    /// Attaches a line number to an error which doesn't have one yet, turning it into a `Parse`
+    /// error with the description of the original error as its message.
+    pub(crate) fn at_line(self, line: usize) -> Error {
+        match self {
+            Error::Parse { .. } | Error::UnexpectedCommand { .. } => self,
+            Error::Msg(message) | Error::MalformedTime(message) => Error::Parse { line, message },
+            error => Error::Parse {
+                line,
+                message: error.to_string(),
+            },
+        }
     }
+}
 
-    links { }
+/// Result type with `Error` as the error.
+pub type Result<T> = result::Result<T, Error>;
 
-    foreign_links {
-        ParseInt(::std::num::ParseIntError)
-            #[doc="Parsing a string into an integer failed."];
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Parse { line, ref message } => write!(f, "line {}: {}", line, message),
+            Error::UnexpectedCommand { line, ref command } => {
+                write!(f, "line {}: Unexpected command {}.", line, command)
+            }
+            Error::MalformedTime(ref message) | Error::Msg(ref message) => f.write_str(message),
+            Error::Io(ref error) => write!(f, "{}", error),
+            Error::ParseInt(ref error) => write!(f, "{}", error),
+        }
     }
+}
 
-    errors { }
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref error) => Some(error),
+            Error::ParseInt(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<ParseIntError> for Error {
+    fn from(error: ParseIntError) -> Self {
+        Error::ParseInt(error)
+    }
+}
+
+impl<'a> From<&'a str> for Error {
+    fn from(message: &'a str) -> Self {
+        Error::Msg(message.to_string())
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::Msg(message)
+    }
 }
@@ -23,9 +23,13 @@
 
 #![deny(missing_docs)]
 
+#[cfg(feature = "serde")]
 #[macro_use]
-extern crate error_chain;
+extern crate serde;
 
+pub mod builder;
+mod encoding;
 pub mod errors;
 pub mod parser;
 pub mod tracklist;
+pub mod validation;
@@ -92,6 +92,50 @@ fn consume_string(tokens: &mut Vec<Token>) -> Result<String, Error> {
 }
 
 impl Command {
+    /// Parses a `REM` line, taking everything after the key verbatim as the value.
+    ///
+    /// Surrounding quotes of the value are removed. Returns `None` if `line` is not a `REM`
+    /// command with a key.
+    pub(crate) fn from_rem_line(line: &str) -> Option<Command> {
+        let line = line.trim_matches(|c: char| c.is_whitespace() || c == '\u{feff}');
+        let mut parts = line.splitn(2, char::is_whitespace);
+        if !parts.next()?.eq_ignore_ascii_case("REM") {
+            return None;
+        }
+
+        let rest = parts.next()?.trim_start();
+        let mut parts = rest.splitn(2, char::is_whitespace);
+        let key = parts.next()?;
+        let value = parts.next().unwrap_or("").trim();
+        let value = if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+            unescape(&value[1..value.len() - 1])
+        } else {
+            value.to_string()
+        };
+
+        Some(Command::Rem(key.to_string(), value))
+    }
+
+    /// Returns true if `word` is the keyword of a command.
+    pub(crate) fn is_keyword(word: &str) -> bool {
+        matches!(
+            word.to_uppercase().as_str(),
+            "CATALOG"
+                | "CDTEXTFILE"
+                | "FILE"
+                | "FLAGS"
+                | "INDEX"
+                | "ISRC"
+                | "PERFORMER"
+                | "POSTGAP"
+                | "PREGAP"
+                | "REM"
+                | "SONGWRITER"
+                | "TITLE"
+                | "TRACK"
+        )
+    }
+
     pub(crate) fn consume(tokens: &mut Vec<Token>) -> Result<Command, Error> {
         let keyword = consume_string(tokens)?;
         match keyword.to_uppercase().as_str() {
@@ -151,3 +195,19 @@ impl Command {
         }
     }
 }
+
+/// Decodes the escape `\"` of a quoted string, other backslashes are kept.
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(&'"')) => {
+                result.push('"');
+                chars.next();
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
@@ -18,33 +18,44 @@
 
 use errors::Error;
 use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::fmt;
-use std::ops::Sub;
+use std::ops::{Add, Sub};
 use std::str::FromStr;
+use std::time::Duration;
 
 mod tokenization;
-use self::tokenization::tokenize;
+use self::tokenization::{tokenize, tokenize_with_options};
 pub use self::tokenization::Token;
 
 mod command;
 pub use self::command::Command;
 
+mod options;
+pub use self::options::ParseOptions;
+
 /// Number of audio frames/sectors per second in cue sheets.
 ///
 /// This value is supposed to be fixed for all cue sheets to 75 frames per second.
 /// TODO: Double-check, how does this interact with the media type?
-const FPS: i64 = 75;
+pub(crate) const FPS: i64 = 75;
 
 /// Time representation of the format `mm:ss:ff`.
 ///
 /// Where mm = minutes, ss = seconds, ff = frames/sectors.
 /// There are 75 frames per second, 60 seconds per minute.
+///
+/// With the `serde` feature a time is serialized as a struct of its `minutes`, `seconds` and
+/// `frames` rather than as a number of frames, so that it stays readable like in the cue sheet.
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Time {
     /// Minutes time component.
+    #[cfg_attr(feature = "serde", serde(rename = "minutes"))]
     mins: i32,
 
     /// Seconds time component.
+    #[cfg_attr(feature = "serde", serde(rename = "seconds"))]
     secs: i8,
 
     /// Frames time component.
@@ -61,6 +72,34 @@ impl Time {
         }
     }
 
+    /// Create a new instance like `new`, but checking that seconds are in `0..60` and frames in
+    /// `0..75`.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// assert_eq!(Time::try_new(5, 47, 50).unwrap(), Time::new(5, 47, 50));
+    /// assert!(Time::try_new(5, 47, 80).is_err());
+    /// assert!(Time::try_new(5, 60, 0).is_err());
+    /// ```
+    pub fn try_new(minutes: i32, seconds: i8, frames: i8) -> Result<Time, Error> {
+        let time = Time::new(minutes, seconds, frames);
+        if !(0..60).contains(&seconds) {
+            return Err(Error::MalformedTime(format!(
+                "Invalid time {}, seconds have to be between 0 and 59.",
+                time
+            )));
+        }
+        if !(0..FPS).contains(&i64::from(frames)) {
+            return Err(Error::MalformedTime(format!(
+                "Invalid time {}, frames have to be between 0 and {}.",
+                time,
+                FPS - 1
+            )));
+        }
+        Ok(time)
+    }
+
     /// Format as `mm:ss' dropping truncating the remainding frames.
     pub fn to_string_2(&self) -> String {
         format!("{:02}:{:02}", self.mins, self.secs)
@@ -120,6 +159,17 @@ impl Time {
         self.frames
     }
 
+    /// Returns the number of frames per second used by cue sheets, which is 75.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// assert_eq!(Time::frames_per_second(), 75);
+    /// ```
+    pub fn frames_per_second() -> u32 {
+        FPS as u32
+    }
+
     /// Returns the total number of minutes represented by this instance.
     ///
     /// ```
@@ -152,6 +202,9 @@ impl Time {
 
     /// Returns the total number of frames/sectors represented by this instance.
     ///
+    /// Cue sheets always use 75 frames per second (see `Time::frames_per_second`), so this is the
+    /// position in units of 1/75 of a second.
+    ///
     /// ```
     /// use cue_sheet::parser::Time;
     ///
@@ -167,6 +220,8 @@ impl Time {
 
     /// Create an instance for the specified number of frames/sectors.
     ///
+    /// This is the inverse of `total_frames`, assuming 75 frames per second.
+    ///
     /// ```
     /// use cue_sheet::parser::Time;
     ///
@@ -185,6 +240,122 @@ impl Time {
             frames: frames as i8,
         }
     }
+
+    /// Subtracts `other` from this time, returning `None` if `other` is the later time.
+    ///
+    /// Unlike the `-` operator, which produces a negative time in that case, this never yields a
+    /// time before `00:00:00`.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// let start = Time::new(1, 0, 0);
+    /// let stop = Time::new(3, 30, 10);
+    /// assert_eq!(stop.checked_sub(&start), Some(Time::new(2, 30, 10)));
+    /// assert_eq!(start.checked_sub(&stop), None);
+    /// ```
+    pub fn checked_sub(&self, other: &Time) -> Option<Time> {
+        let diff = self.total_frames() - other.total_frames();
+        if diff < 0 {
+            None
+        } else {
+            Some(Time::from_frames(diff))
+        }
+    }
+
+    /// Returns true if this time and `other` are at most `tolerance_frames` frames apart.
+    ///
+    /// Index times of different rips of the same disc often differ by a few frames.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// let time = Time::new(5, 47, 50);
+    /// assert!(time.approx_eq(Time::new(5, 47, 52), 5));
+    /// assert!(!time.approx_eq(Time::new(5, 47, 52), 1));
+    /// ```
+    pub fn approx_eq(&self, other: Time, tolerance_frames: u32) -> bool {
+        (self.total_frames() - other.total_frames()).abs() <= i64::from(tolerance_frames)
+    }
+
+    /// Converts the time into a `Duration`.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    /// use std::time::Duration;
+    ///
+    /// assert_eq!(Time::new(1, 30, 0).as_duration(), Duration::from_secs(90));
+    /// assert_eq!(Time::new(0, 0, 15).as_duration(), Duration::from_millis(200));
+    /// ```
+    pub fn as_duration(&self) -> Duration {
+        let frames = self.total_frames().max(0);
+        let nanos = (frames % FPS) * 1_000_000_000 / FPS;
+        Duration::new((frames / FPS) as u64, nanos as u32)
+    }
+
+    /// Creates an instance from a `Duration`, rounded to the nearest frame.
+    ///
+    /// A frame is 1/75 of a second, so precision below that is lost in the conversion.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    /// use std::time::Duration;
+    ///
+    /// let duration = Duration::from_millis(123_456);
+    /// let time = Time::from_duration(duration);
+    /// assert_eq!(time, Time::new(2, 3, 34));
+    /// assert!(duration - time.as_duration() < Duration::from_millis(14));
+    /// ```
+    pub fn from_duration(duration: Duration) -> Time {
+        let nanos = duration.subsec_nanos() as i64;
+        let frames = (nanos * FPS + 500_000_000) / 1_000_000_000;
+        Time::from_frames(duration.as_secs() as i64 * FPS + frames)
+    }
+
+    /// Returns the same point in time with seconds below 60 and frames below 75.
+    ///
+    /// Lenient parsing accepts times like `03:70:80`, which are carried over into the higher
+    /// components here.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// let time = Time::new(3, 70, 80);
+    /// assert_eq!(time.normalized(), Time::new(4, 11, 5));
+    /// ```
+    pub fn normalized(&self) -> Time {
+        Time::from_frames(self.total_frames())
+    }
+
+    /// Returns the number of CD sectors represented by this instance.
+    ///
+    /// On audio CDs one frame corresponds to exactly one sector, so this is the same value as
+    /// `total_frames`, provided for clarity when dealing with data discs. Returns `None` for a
+    /// negative time, e.g. the result of subtracting a later time, or one too long for a `u32`.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// let time = Time::new(1, 2, 3);
+    /// assert_eq!(time.to_sectors(), Some(4653));
+    /// assert_eq!((Time::new(0, 0, 0) - time).to_sectors(), None);
+    /// ```
+    pub fn to_sectors(&self) -> Option<u32> {
+        u32::try_from(self.total_frames()).ok()
+    }
+
+    /// Create an instance for the specified number of CD sectors.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// let time = Time::from_sectors(4653);
+    /// assert_eq!(time, Time::new(1, 2, 3));
+    /// assert_eq!(time, Time::from_frames(4653));
+    /// ```
+    pub fn from_sectors(sectors: u32) -> Time {
+        Time::from_frames(sectors as i64)
+    }
 }
 
 impl Ord for Time {
@@ -202,19 +373,53 @@ impl PartialOrd for Time {
 impl FromStr for Time {
     type Err = Error;
 
+    /// Parses a time in the canonical `MM:SS:FF` form, e.g. `"05:47:50"`.
+    ///
+    /// Seconds have to be less than 60 and frames less than 75.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let time = Time::parse_fields(s)?;
+        if time.secs >= 60 {
+            return Err(Error::MalformedTime(format!(
+                "Invalid time {:?}, seconds have to be less than 60.",
+                s
+            )));
+        }
+        if time.frames as i64 >= FPS {
+            return Err(Error::MalformedTime(format!(
+                "Invalid time {:?}, frames have to be less than {}.",
+                s, FPS
+            )));
+        }
+        Ok(time)
+    }
+}
+
+impl Time {
+    /// Parses the fields of a time in the `MM:SS:FF` form without checking their ranges.
+    ///
+    /// The tokenizer uses this so cue sheets with more frames per second can be read, the frames
+    /// are validated against `ParseOptions::frames_per_second` afterwards.
+    pub(crate) fn parse_fields(s: &str) -> Result<Time, Error> {
         if s.len() != 8 {
-            return Err("Time was not 8 chars long.".into());
+            return Err(Error::MalformedTime(format!(
+                "Time {:?} was not 8 chars long.",
+                s
+            )));
         }
 
-        if s.chars().nth(2).unwrap() != ':' || s.chars().nth(5).unwrap() != ':' {
-            return Err("Time was not properly formatted.".into());
+        let fields: Vec<&str> = s.split(':').collect();
+        let digits = |field: &&str| field.len() == 2 && field.chars().all(|c| c.is_ascii_digit());
+        if fields.len() != 3 || !fields.iter().all(digits) {
+            return Err(Error::MalformedTime(format!(
+                "Time {:?} was not properly formatted.",
+                s
+            )));
         }
 
         Ok(Time {
-            mins: s[0..2].parse()?,
-            secs: s[3..5].parse()?,
-            frames: s[6..8].parse()?,
+            mins: fields[0].parse()?,
+            secs: fields[1].parse()?,
+            frames: fields[2].parse()?,
         })
     }
 }
@@ -225,6 +430,14 @@ impl fmt::Display for Time {
     }
 }
 
+impl Add for Time {
+    type Output = Time;
+
+    fn add(self, rhs: Time) -> Self::Output {
+        Time::from_frames(self.total_frames() + rhs.total_frames())
+    }
+}
+
 impl Sub for Time {
     type Output = Time;
 
@@ -239,6 +452,7 @@ impl Sub for Time {
 
 /// Describes the file format of an audio file.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FileFormat {
     /// Also includes other lossless formats.
     Wave,
@@ -271,8 +485,22 @@ impl FromStr for FileFormat {
     }
 }
 
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let keyword = match *self {
+            FileFormat::Wave => "WAVE",
+            FileFormat::Mp3 => "MP3",
+            FileFormat::Aiff => "AIFF",
+            FileFormat::Binary => "BINARY",
+            FileFormat::Motorola => "MOTOROLA",
+        };
+        f.write_str(keyword)
+    }
+}
+
 /// Additional flags a Track can have.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum TrackFlag {
     /// Digital Copy Permitted
     Dcp,
@@ -285,6 +513,9 @@ pub enum TrackFlag {
 
     /// Serial Copy Management System
     Scms,
+
+    /// A flag not defined by the cue sheet format, kept as written.
+    Other(String),
 }
 
 impl FromStr for TrackFlag {
@@ -296,15 +527,30 @@ impl FromStr for TrackFlag {
             "4CH" => Ok(TrackFlag::FourChannel),
             "PRE" => Ok(TrackFlag::Pre),
             "SCMS" => Ok(TrackFlag::Scms),
-            s => Err(format!("invalid TrackFlag: {:?}", s).into()),
+            "" => Err("invalid TrackFlag: \"\"".into()),
+            _ => Ok(TrackFlag::Other(s.to_string())),
         }
     }
 }
 
+impl fmt::Display for TrackFlag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let keyword = match *self {
+            TrackFlag::Dcp => "DCP",
+            TrackFlag::FourChannel => "4CH",
+            TrackFlag::Pre => "PRE",
+            TrackFlag::Scms => "SCMS",
+            TrackFlag::Other(ref flag) => flag,
+        };
+        f.write_str(keyword)
+    }
+}
+
 /// Describes the type of tracks on the media.
 ///
 /// Most of the times for music this will be just `Audio`.
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum TrackType {
     /// Audio/Music (2352 — 588 samples)
     Audio,
@@ -334,10 +580,10 @@ impl FromStr for TrackType {
             "CDG" => Ok(TrackType::Cdg),
             "MODE1/2048" => Ok(TrackType::Mode(1, 2048)),
             "MODE1/2352" => Ok(TrackType::Mode(1, 2352)),
-            "MODE2/2048" => Ok(TrackType::Mode(1, 2048)),
-            "MODE2/2324" => Ok(TrackType::Mode(1, 2324)),
-            "MODE2/2336" => Ok(TrackType::Mode(1, 2336)),
-            "MODE2/2352" => Ok(TrackType::Mode(1, 2352)),
+            "MODE2/2048" => Ok(TrackType::Mode(2, 2048)),
+            "MODE2/2324" => Ok(TrackType::Mode(2, 2324)),
+            "MODE2/2336" => Ok(TrackType::Mode(2, 2336)),
+            "MODE2/2352" => Ok(TrackType::Mode(2, 2352)),
             "CDI/2336" => Ok(TrackType::Cdi(2336)),
             "CDI/2352" => Ok(TrackType::Cdi(2352)),
             _ => Err(format!("Unknown track type: {:?}", s).into()),
@@ -345,14 +591,173 @@ impl FromStr for TrackType {
     }
 }
 
+impl fmt::Display for TrackType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TrackType::Audio => f.write_str("AUDIO"),
+            TrackType::Cdg => f.write_str("CDG"),
+            TrackType::Mode(mode, size) => write!(f, "MODE{}/{}", mode, size),
+            TrackType::Cdi(size) => write!(f, "CDI/{}", size),
+        }
+    }
+}
+
+/// Formats `value` as a string argument of a command, quoting it only if the tokenizer would not
+/// read it back as the same string otherwise.
+pub(crate) fn format_string(value: &str) -> String {
+    match tokenize(value) {
+        Ok(ref tokens) if tokens.len() == 1 && tokens[0] == Token::String(value.to_string()) => {
+            value.to_string()
+        }
+        _ => quote_string(value),
+    }
+}
+
+/// Formats `value` as a quoted string argument of a command, escaping quotes inside of it with a
+/// backslash.
+///
+/// Other backslashes are written as they are, since most tools read paths like `C:\Music`
+/// literally.
+pub(crate) fn quote_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\\\""))
+}
+
 /// Parse CUE sheet provided by the parameter `source`.
 pub fn parse_cue(source: &str) -> Result<Vec<Command>, Error> {
-    let mut tokens = tokenize(source)?;
+    parse_cue_with_options(source, &ParseOptions::default())
+}
+
+/// Parse CUE sheet provided by the parameter `source` using the specified `options`.
+pub fn parse_cue_with_options(source: &str, options: &ParseOptions) -> Result<Vec<Command>, Error> {
+    let commands = parse_commands(source, options, &mut Vec::new())?;
+    Ok(commands.into_iter().map(|(_, command)| command).collect())
+}
+
+/// Parses the commands of a cue sheet, every command is expected on its own line.
+///
+/// Every command is returned together with its line number. Problems which are tolerated because
+/// `options` are not strict are added to `warnings`, in particular unknown commands are skipped.
+pub(crate) fn parse_commands(
+    source: &str,
+    options: &ParseOptions,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<(usize, Command)>, Error> {
     let mut commands = Vec::new();
+    let mut track_count = 0;
+
+    // Lines are split at CR too, so files with classic Mac line endings work.
+    for (i, line) in source.lines().flat_map(|l| l.split('\r')).enumerate() {
+        let line = options.resolve_alias(line);
+        let line = line.as_ref();
+        if let Some(command) = Command::from_rem_line(line) {
+            commands.push((i + 1, command));
+            continue;
+        }
+
+        let mut tokens = tokenize_with_options(line, options).map_err(|e| e.at_line(i + 1))?;
+        if tokens.is_empty() {
+            continue;
+        }
 
-    while tokens.len() > 0 {
-        commands.push(Command::consume(&mut tokens)?);
+        for token in &tokens {
+            if let Token::Time(ref time) = *token {
+                if time.frames() as i64 >= options.frames_per_second as i64 {
+                    return Err(Error::Parse {
+                        line: i + 1,
+                        message: format!(
+                            "Invalid time {}, frames have to be less than {}.",
+                            time, options.frames_per_second
+                        ),
+                    });
+                }
+            }
+        }
+
+        if let Token::String(ref keyword) = tokens[0] {
+            if !Command::is_keyword(keyword) {
+                if options.strict {
+                    return Err(Error::UnexpectedCommand {
+                        line: i + 1,
+                        command: format!("{:?}", keyword),
+                    });
+                }
+                warnings.push(format!("line {}: Unknown command {:?}.", i + 1, keyword));
+                continue;
+            }
+        }
+
+        let command = Command::consume(&mut tokens).map_err(|e| e.at_line(i + 1))?;
+        if !tokens.is_empty() {
+            let message = format!("Unexpected trailing data: {:?}", tokens);
+            if options.strict {
+                return Err(Error::Parse {
+                    line: i + 1,
+                    message,
+                });
+            }
+            warnings.push(format!("line {}: {}", i + 1, message));
+        }
+
+        if let Command::Track(..) = command {
+            track_count += 1;
+            if let Some(max_tracks) = options.max_tracks {
+                if track_count > max_tracks {
+                    return Err(Error::Parse {
+                        line: i + 1,
+                        message: format!("More than {} tracks in cue sheet.", max_tracks),
+                    });
+                }
+            }
+        }
+        commands.push((i + 1, command));
     }
 
     Ok(commands)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_from_str() {
+        assert_eq!("00:00:00".parse::<Time>().unwrap(), Time::new(0, 0, 0));
+        assert_eq!("99:59:74".parse::<Time>().unwrap(), Time::new(99, 59, 74));
+        assert!("05:47:80".parse::<Time>().is_err());
+        assert!("05:60:00".parse::<Time>().is_err());
+        assert!("5:47".parse::<Time>().is_err());
+        assert!("05:4a:50".parse::<Time>().is_err());
+        assert!("05-47-50".parse::<Time>().is_err());
+    }
+
+    #[test]
+    fn time_display() {
+        assert_eq!(Time::new(5, 47, 50).to_string(), "05:47:50");
+        assert_eq!(Time::new(123, 0, 0).to_string(), "123:00:00");
+    }
+
+    #[test]
+    fn total_frames_round_trip() {
+        assert_eq!(Time::new(1, 0, 0).total_frames(), 4500);
+        assert_eq!(Time::from_frames(4500), Time::new(1, 0, 0));
+
+        let time = Time::new(12, 34, 56);
+        assert_eq!(Time::from_frames(time.total_frames()), time);
+    }
+
+    #[test]
+    fn time_ordering() {
+        assert!(Time::new(0, 5, 0) < Time::new(0, 5, 1));
+        assert!(Time::new(0, 5, 74) < Time::new(0, 6, 0));
+        assert!(Time::new(0, 59, 74) < Time::new(1, 0, 0));
+        assert!(Time::new(10, 0, 0) > Time::new(9, 59, 74));
+
+        let mut times = vec![Time::new(1, 0, 0), Time::new(0, 0, 1), Time::new(0, 1, 0)];
+        times.sort();
+        assert_eq!(
+            times,
+            vec![Time::new(0, 0, 1), Time::new(0, 1, 0), Time::new(1, 0, 0)]
+        );
+        assert_eq!(times.iter().max(), Some(&Time::new(1, 0, 0)));
+    }
+}
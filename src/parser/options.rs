@@ -0,0 +1,84 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Options controlling how cue sheets are parsed.
+#[derive(Clone, Debug)]
+pub struct ParseOptions {
+    /// Maximum number of `TRACK` commands accepted before parsing is aborted with an error.
+    ///
+    /// Set this when parsing untrusted input to guard against crafted files with an excessive
+    /// number of tracks. `None` (the default) means no limit.
+    pub max_tracks: Option<usize>,
+
+    /// Number of frames per second the times in the cue sheet are expected to use.
+    ///
+    /// Frame components of times have to be smaller than this value. It defaults to the 75 frames
+    /// per second of CDs, but some non-CD cue-like files use 100. Note that this only affects which
+    /// times are accepted, all calculations with `Time` still assume 75 frames per second.
+    pub frames_per_second: u8,
+
+    /// Reject cue sheets with recoverable problems instead of tolerating them.
+    ///
+    /// When parsing leniently (the default) such problems are reported as warnings.
+    pub strict: bool,
+
+    /// Accept the typographic quotes `“` and `”` as string delimiters in lenient mode.
+    ///
+    /// Titles copied from web pages sometimes use them instead of `"`.
+    pub smart_quotes: bool,
+
+    /// Nonstandard keywords mapped onto the standard keywords they stand for, e.g. `"AUDIOFILE"`
+    /// to `"FILE"`.
+    ///
+    /// Keywords are matched case-insensitively and replaced before a line is tokenized.
+    pub keyword_aliases: HashMap<String, String>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            max_tracks: None,
+            frames_per_second: 75,
+            strict: false,
+            smart_quotes: false,
+            keyword_aliases: HashMap::new(),
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Replaces the keyword at the start of `line` if it is one of the `keyword_aliases`.
+    pub(crate) fn resolve_alias<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        if self.keyword_aliases.is_empty() {
+            return Cow::Borrowed(line);
+        }
+
+        let line = line.trim_start_matches(|c: char| c.is_whitespace() || c == '\u{feff}');
+        let end = line.find(char::is_whitespace).unwrap_or(line.len());
+        let keyword = &line[..end];
+        match self
+            .keyword_aliases
+            .iter()
+            .find(|&(alias, _)| alias.eq_ignore_ascii_case(keyword))
+        {
+            Some((_, standard)) => Cow::Owned(format!("{}{}", standard, &line[end..])),
+            None => Cow::Borrowed(line),
+        }
+    }
+}
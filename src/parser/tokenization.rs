@@ -15,7 +15,7 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use errors::Error;
-use parser::Time;
+use parser::{ParseOptions, Time};
 
 /// Any token as it can appear in a cue sheet.
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -34,6 +34,9 @@ pub enum Token {
 struct Reader {
     chars: Vec<char>,
     position: usize,
+
+    /// Whether `“` and `”` are accepted as string delimiters.
+    smart_quotes: bool,
 }
 
 const DIGITS: [char; 10] = ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
@@ -47,6 +50,7 @@ impl Reader {
         Reader {
             chars: source.chars().collect(),
             position: 0,
+            smart_quotes: false,
         }
     }
 
@@ -65,18 +69,28 @@ impl Reader {
         }
     }
 
-    fn take(&mut self, n: usize) -> Result<String, Error> {
-        self.peek(n).map(|s| {
-            self.position += n;
-            s
-        })
+    /// Returns the next char without consuming it.
+    fn peek_char(&self) -> Option<char> {
+        self.chars.get(self.position).cloned()
+    }
+
+    /// Consumes and returns the next char.
+    ///
+    /// Unlike `peek` this doesn't allocate, so scanning a line char by char stays cheap.
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.peek_char()?;
+        self.position += 1;
+        Some(c)
     }
 
     fn try_take_time(&mut self) -> Option<Time> {
-        self.peek(8).ok().and_then(|s| s.parse().ok()).map(|time| {
-            self.position += 8;
-            time
-        })
+        self.peek(8)
+            .ok()
+            .and_then(|s| Time::parse_fields(&s).ok())
+            .map(|time| {
+                self.position += 8;
+                time
+            })
     }
 
     // notice that numbers can only be two digits long
@@ -106,25 +120,53 @@ impl Reader {
         }
     }
 
+    /// Reads a quoted or unquoted string.
+    ///
+    /// Inside of quotes `\"` stands for `"`, any other backslash is kept. If the string isn't
+    /// closed with escapes decoded, it is read again with backslashes taken literally, so paths
+    /// like `"C:\Music\"` still work.
     fn take_string(&mut self) -> Result<String, Error> {
-        let mut result = Vec::new();
+        let start = self.position;
+        match self.take_string_with(true) {
+            Err(_) => {
+                self.position = start;
+                self.take_string_with(false)
+            }
+            result => result,
+        }
+    }
+
+    fn take_string_with(&mut self, escapes: bool) -> Result<String, Error> {
+        let mut result = String::new();
 
         // Check if string is quoted.
-        let first = self.take(1)?.chars().next().unwrap();
-        let is_quoted = first == '"';
+        let first = self
+            .next_char()
+            .ok_or("Tried to read out of bounds of reader.")?;
+        let closing_quote = match first {
+            '"' => Some('"'),
+            '\u{201c}' if self.smart_quotes => Some('\u{201d}'),
+            _ => None,
+        };
+        let is_quoted = closing_quote.is_some();
         if !is_quoted {
             result.push(first);
         }
 
         // Now read as many chars as possible.
-        while let Ok(next) = self.take(1) {
-            let next = next.chars().next().unwrap();
-            if next == '"' {
-                if is_quoted {
-                    return Ok(result.into_iter().collect());
-                } else {
-                    return Err("The `\"` char is not allowed in strings.".into());
+        while let Some(next) = self.next_char() {
+            if Some(next) == closing_quote {
+                return Ok(result);
+            } else if is_quoted && escapes && next == '\\' {
+                match self.peek_char() {
+                    Some('"') => {
+                        self.next_char();
+                        result.push('"');
+                    }
+                    _ => result.push(next),
                 }
+            } else if next == '"' && !is_quoted {
+                return Err("The `\"` char is not allowed in strings.".into());
             } else if !is_quoted && is_whitespace(next) {
                 break;
             } else {
@@ -135,13 +177,27 @@ impl Reader {
         if is_quoted {
             Err("Opened string not closed until EOF.".into())
         } else {
-            Ok(result.into_iter().collect())
+            Ok(result)
+        }
+    }
+
+    /// Skips a comment starting with `;` up to the end of the line, returns true if there was one.
+    fn try_skip_comment(&mut self) -> bool {
+        match self.peek_char() {
+            Some(';') => {
+                while let Some(next) = self.next_char() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+                true
+            }
+            _ => false,
         }
     }
 
     fn try_skip_whitespace(&mut self) {
-        while let Ok(next) = self.peek(1) {
-            let next = next.chars().next().unwrap();
+        while let Some(next) = self.peek_char() {
             if is_whitespace(next) {
                 self.position += 1;
             } else {
@@ -153,12 +209,20 @@ impl Reader {
 
 /// Converts a string into a vector of tokens.
 pub fn tokenize(source: &str) -> Result<Vec<Token>, Error> {
+    tokenize_with_options(source, &ParseOptions::default())
+}
+
+/// Converts a string into a vector of tokens using the specified `options`.
+pub fn tokenize_with_options(source: &str, options: &ParseOptions) -> Result<Vec<Token>, Error> {
     let mut tokens = Vec::new();
     let mut reader = Reader::new(source);
+    reader.smart_quotes = options.smart_quotes && !options.strict;
 
     reader.try_skip_whitespace();
     while reader.available() {
-        if let Some(time) = reader.try_take_time() {
+        if reader.try_skip_comment() {
+            // Comments are not part of the token stream.
+        } else if let Some(time) = reader.try_take_time() {
             tokens.push(Token::Time(time));
         } else if let Some(num) = reader.try_take_number() {
             tokens.push(Token::Number(num));
@@ -213,6 +277,18 @@ mod tests {
 
         let mut r2 = Reader::new("\"abc\"");
         assert_eq!(r2.take_string().unwrap(), "abc".to_string());
+
+        let mut r3 = Reader::new(r#""She said \"hi\"" C:\Music"#);
+        assert_eq!(r3.take_string().unwrap(), "She said \"hi\"".to_string());
+
+        let mut r4 = Reader::new(r#""C:\Music\a.wav""#);
+        assert_eq!(r4.take_string().unwrap(), r"C:\Music\a.wav".to_string());
+
+        let mut r5 = Reader::new(r#""\\nas\music\a.wav" WAVE"#);
+        assert_eq!(r5.take_string().unwrap(), r"\\nas\music\a.wav".to_string());
+
+        let mut r6 = Reader::new(r#""C:\Music\" WAVE"#);
+        assert_eq!(r6.take_string().unwrap(), r"C:\Music\".to_string());
     }
 
     #[test]
@@ -239,4 +315,40 @@ mod tests {
         assert_eq!(tokens[1], Token::String("xyz xyz 12 10:10:30".to_string()));
         assert_eq!(tokens[2], Token::String(" abc ".to_string()));
     }
+
+    #[test]
+    fn comments() {
+        let source = "PERFORMER \"Marillion\" ; band\nTITLE ;\n\"x;y\"";
+        let tokens = tokenize(source).unwrap();
+
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[0], Token::String("PERFORMER".to_string()));
+        assert_eq!(tokens[1], Token::String("Marillion".to_string()));
+        assert_eq!(tokens[2], Token::String("TITLE".to_string()));
+        assert_eq!(tokens[3], Token::String("x;y".to_string()));
+    }
+
+    #[test]
+    fn smart_quotes() {
+        let mut r1 = Reader::new("\u{201c}a \"b\"\u{201d}");
+        r1.smart_quotes = true;
+        assert_eq!(r1.take_string().unwrap(), "a \"b\"".to_string());
+
+        let mut r2 = Reader::new("\u{201c}a\u{201d}");
+        assert_eq!(r2.take_string().unwrap(), "\u{201c}a\u{201d}".to_string());
+    }
+
+    #[test]
+    fn long_line() {
+        // Scanning has to stay linear in the length of the line, a quadratic tokenizer would take
+        // ages for this.
+        let title = "Lorem ipsum dolor sit amet ".repeat(40_000);
+        let line = format!("TITLE \"{}\"", title);
+        let tokens = tokenize(&line).unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[1], Token::String(title));
+
+        let word = "x".repeat(1_000_000);
+        assert_eq!(tokenize(&word).unwrap(), vec![Token::String(word)]);
+    }
 }
@@ -18,11 +18,29 @@
 
 // TODO don't swallow errors in parsing but use Result and Option where appropriate.
 
+use encoding;
 use errors::Error;
-use parser::{self, Command, FileFormat, Time, TrackType};
+use parser::{
+    self, format_string, quote_string, Command, FileFormat, ParseOptions, Time, TrackFlag,
+    TrackType, FPS,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use validation::{self, ValidationError, ValidationReport};
+
+/// Maximum difference in frames between track starts for `Tracklist::same_disc`.
+const SAME_DISC_TOLERANCE: u32 = 5;
 
 /// A tracklist provides a more useful representation of the information of a cue sheet.
-#[derive(Clone, Debug)]
+///
+/// Formatting a tracklist with `Display` produces the text of a cue sheet describing it.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Tracklist {
     /// 13 decimal digit UPC/EAN code
     pub catalog: Option<String>,
@@ -30,12 +48,18 @@ pub struct Tracklist {
     /// Files described by the tracklist.
     pub files: Vec<TrackFile>,
 
+    /// Name of an external file with the CD-TEXT data of the disc, from `CDTEXTFILE`.
+    pub cdtextfile: Option<String>,
+
     /// Performer of the tracklist.
     pub performer: Option<String>,
 
     /// Title of the tracklist.
     pub title: Option<String>,
 
+    /// Songwriter of the tracklist.
+    pub songwriter: Option<String>,
+
     /// Genre of the tracklist.
     pub genre: Option<String>,
 
@@ -54,29 +78,313 @@ pub struct Tracklist {
 
     /// DiscID of the tracklist.
     pub totaldiscs: Option<u8>,
+
+    /// Character encoding declared by a `REM ENCODING` line, if any.
+    pub encoding: Option<String>,
+
+    /// Rating of the tracklist as stored by some players with `REM RATING`.
+    pub rating: Option<u8>,
+
+    /// End of the last track as declared by a `REM LEAD-OUT` or `REM LEADOUT` line.
+    ///
+    /// If present, it determines the duration of the last track.
+    pub leadout: Option<Time>,
+
+    /// All REM fields which are not mapped to one of the fields above, keyed by the uppercased
+    /// tag, e.g. `REPLAYGAIN_ALBUM_GAIN`.
+    pub rem: HashMap<String, String>,
+
+    /// Problems which were tolerated while parsing the cue sheet leniently.
+    ///
+    /// These describe the source rather than the tracklist, so they are neither compared nor
+    /// serialized.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub warnings: Vec<String>,
+}
+
+impl PartialEq for Tracklist {
+    fn eq(&self, other: &Tracklist) -> bool {
+        self.catalog == other.catalog
+            && self.files == other.files
+            && self.cdtextfile == other.cdtextfile
+            && self.performer == other.performer
+            && self.title == other.title
+            && self.songwriter == other.songwriter
+            && self.genre == other.genre
+            && self.date == other.date
+            && self.discid == other.discid
+            && self.comment == other.comment
+            && self.discnumber == other.discnumber
+            && self.totaldiscs == other.totaldiscs
+            && self.encoding == other.encoding
+            && self.rating == other.rating
+            && self.leadout == other.leadout
+            && self.rem == other.rem
+    }
 }
 
 impl Tracklist {
     /// Parse a cue sheet (content provided as `source`) into a `Tracklist`.
     pub fn parse(source: &str) -> Result<Tracklist, Error> {
-        let mut commands = parser::parse_cue(source)?;
+        Tracklist::parse_with_options(source, &ParseOptions::default())
+    }
+
+    /// Parse a cue sheet (content provided as `source`) into a `Tracklist`, returning an error for
+    /// the first unknown or unexpected command instead of skipping it.
+    ///
+    /// This is the same as parsing with `ParseOptions::strict` set.
+    pub fn parse_strict(source: &str) -> Result<Tracklist, Error> {
+        let options = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        Tracklist::parse_with_options(source, &options)
+    }
+
+    /// Parse a cue sheet provided as raw bytes into a `Tracklist`.
+    ///
+    /// If the cue sheet declares its encoding with `REM ENCODING` that encoding is used, otherwise
+    /// the content is decoded as UTF-8 if possible and as Windows-1252 if not.
+    pub fn parse_bytes(source: &[u8]) -> Result<Tracklist, Error> {
+        let hint = encoding::encoding_hint(source);
+        let source = encoding::decode(source, hint.as_deref());
+        Tracklist::parse(&source)
+    }
+
+    /// Read the cue sheet at `path` and parse it into a `Tracklist`.
+    ///
+    /// The content is decoded the same way as by `Tracklist::parse_bytes`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Tracklist, Error> {
+        let source = fs::read(path)?;
+        Tracklist::parse_bytes(&source)
+    }
+
+    /// Parse a cue sheet embedded into an audio file, e.g. the value of a `CUESHEET` Vorbis comment.
+    ///
+    /// A leading `CUESHEET=` as found in exported tag listings is skipped.
+    pub fn parse_embedded(source: &str) -> Result<Tracklist, Error> {
+        let prefix = "CUESHEET=";
+        let source = source.trim_start();
+        match source.get(..prefix.len()) {
+            Some(start) if start.eq_ignore_ascii_case(prefix) => {
+                Tracklist::parse(&source[prefix.len()..])
+            }
+            _ => Tracklist::parse(source),
+        }
+    }
+
+    /// Parse a `.toc` file as written by cdrdao into a `Tracklist`.
+    ///
+    /// The `FILE` (or `AUDIOFILE`) statement of every track is mapped onto the index times of a
+    /// cue sheet: the data start becomes `INDEX 00` if there is a `START`, and `INDEX 01` is at the
+    /// `START` offset. A `PREGAP` of silence is represented like the cue `PREGAP` command. Further
+    /// `INDEX` statements are relative to `INDEX 01`. Consecutive tracks from the same file are
+    /// grouped into one `TrackFile`. `COPY`, `PRE_EMPHASIS` and `FOUR_CHANNEL_AUDIO` become the
+    /// track flags `DCP`, `PRE` and `4CH`.
+    ///
+    /// `CD_TEXT` blocks and statements without an equivalent in cue sheets are skipped with a
+    /// warning.
+    pub fn parse_toc(source: &str) -> Result<Tracklist, Error> {
+        let mut tracklist = Tracklist::default();
+        let mut tracks: Vec<TocTrack> = Vec::new();
+        let mut depth = 0;
+
+        for (i, line) in source.lines().enumerate() {
+            let words = toc_words(line);
+            let braces = |c| words.iter().filter(|w| w.as_str() == c).count();
+            if depth > 0 || braces("{") > 0 || braces("}") > 0 {
+                depth = (depth + braces("{"))
+                    .checked_sub(braces("}"))
+                    .ok_or_else(|| Error::Parse {
+                        line: i + 1,
+                        message: "Unbalanced `}`.".to_string(),
+                    })?;
+                continue;
+            }
+            let keyword = match words.first() {
+                Some(keyword) => keyword.to_uppercase(),
+                None => continue,
+            };
+            let arg = |n: usize| {
+                words.get(n).ok_or_else(|| Error::Parse {
+                    line: i + 1,
+                    message: "Missing argument.".to_string(),
+                })
+            };
+            let time = |value: &str| parse_toc_time(value).map_err(|e| e.at_line(i + 1));
+            let start_of = |track: &Track| match track.index_time(1) {
+                Some(start) => Ok(start.total_frames()),
+                None => Err(Error::Parse {
+                    line: i + 1,
+                    message: format!("{} before FILE.", keyword),
+                }),
+            };
+
+            match (keyword.as_str(), tracks.last_mut()) {
+                ("CD_DA", _) | ("CD_ROM", _) | ("CD_ROM_XA", _) => {}
+                ("CATALOG", None) => tracklist.catalog = Some(arg(1)?.clone()),
+                ("TRACK", _) => tracks.push(TocTrack {
+                    track: Track {
+                        title: None,
+                        track_type: toc_track_type(arg(1)?).map_err(|e| e.at_line(i + 1))?,
+                        duration: None,
+                        index: Vec::new(),
+                        number: tracks.len() as u32 + 1,
+                        performer: None,
+                        songwriter: None,
+                        isrc: None,
+                        date: None,
+                        flags: Vec::new(),
+                        postgap: None,
+                    },
+                    file: String::new(),
+                    end: None,
+                    pregap: 0,
+                }),
+                ("ISRC", Some(toc)) => toc.track.isrc = Some(arg(1)?.to_uppercase()),
+                ("PREGAP", Some(toc)) => toc.pregap = time(arg(1)?)?.total_frames(),
+                ("COPY", Some(toc)) => toc.track.flags.push(TrackFlag::Dcp),
+                ("PRE_EMPHASIS", Some(toc)) => toc.track.flags.push(TrackFlag::Pre),
+                ("FOUR_CHANNEL_AUDIO", Some(toc)) => toc.track.flags.push(TrackFlag::FourChannel),
+                // The defaults, a track without flags.
+                ("NO", Some(_)) | ("TWO_CHANNEL_AUDIO", Some(_)) => {}
+                ("FILE", Some(toc)) | ("AUDIOFILE", Some(toc)) => {
+                    toc.file = arg(1)?.clone();
+                    let start = time(arg(2)?)?.total_frames();
+                    toc.track.index = vec![(1, Time::from_frames(start))];
+                    if let Some(length) = words.get(3) {
+                        toc.end = Some(start + time(length)?.total_frames());
+                    }
+                }
+                ("START", Some(toc)) => {
+                    let data_start = start_of(&toc.track)?;
+                    let offset = time(arg(1)?)?.total_frames();
+                    toc.track.index = vec![
+                        (0, Time::from_frames(data_start)),
+                        (1, Time::from_frames(data_start + offset)),
+                    ];
+                }
+                ("INDEX", Some(toc)) => {
+                    let start = start_of(&toc.track)?;
+                    let offset = time(arg(1)?)?.total_frames();
+                    let track = &mut toc.track;
+                    let number = track.index.iter().map(|index| index.0).max().unwrap_or(1) + 1;
+                    track
+                        .index
+                        .push((number, Time::from_frames(start + offset)));
+                }
+                _ => {
+                    tracklist.warnings.push(format!(
+                        "line {}: Ignoring unsupported toc statement {}.",
+                        i + 1,
+                        keyword
+                    ));
+                }
+            }
+        }
+
+        for i in 0..tracks.len() {
+            let start = match tracks[i].track.index_time(1) {
+                Some(start) => start.total_frames(),
+                None => continue,
+            };
+            let end = tracks[i].end.or_else(|| {
+                let next = tracks.get(i + 1)?;
+                if next.file != tracks[i].file {
+                    return None;
+                }
+                next.track.index.first().map(|index| index.1.total_frames())
+            });
+
+            let toc = &mut tracks[i];
+            toc.track.duration = end.map(|end| Time::from_frames(end - start));
+            // Like the cue `PREGAP`, silence is represented by an `INDEX 00` before `INDEX 01`.
+            if toc.pregap > 0 && toc.track.index_time(0).is_none() {
+                let pregap = Time::from_frames((start - toc.pregap).max(0));
+                toc.track.index.insert(0, (0, pregap));
+            }
+        }
+
+        for toc in tracks {
+            let same_file = match tracklist.files.last() {
+                Some(file) => file.name == toc.file,
+                None => false,
+            };
+            if !same_file {
+                let name = toc.file.to_lowercase();
+                let format = if name.ends_with(".bin") || name.ends_with(".raw") {
+                    FileFormat::Binary
+                } else {
+                    FileFormat::Wave
+                };
+                tracklist.files.push(TrackFile {
+                    tracks: Vec::new(),
+                    name: toc.file.clone(),
+                    format,
+                });
+            }
+            if let Some(file) = tracklist.files.last_mut() {
+                file.tracks.push(toc.track);
+            }
+        }
+
+        Ok(tracklist)
+    }
+
+    /// Parse a cue sheet (content provided as `source`) into a `Tracklist` using the specified
+    /// `options`.
+    pub fn parse_with_options(source: &str, options: &ParseOptions) -> Result<Tracklist, Error> {
+        Tracklist::parse_internal(source, options, &mut |_| {})
+    }
+
+    /// Parse a cue sheet (content provided as `source`) into a `Tracklist`, calling `callback` with
+    /// a `ParseEvent` whenever parsing makes progress.
+    pub fn parse_with_callback<F: FnMut(ParseEvent)>(
+        source: &str,
+        mut callback: F,
+    ) -> Result<Tracklist, Error> {
+        Tracklist::parse_internal(source, &ParseOptions::default(), &mut callback)
+    }
+
+    fn parse_internal<F: FnMut(ParseEvent)>(
+        source: &str,
+        options: &ParseOptions,
+        callback: &mut F,
+    ) -> Result<Tracklist, Error> {
+        let mut warnings = Vec::new();
+        let (mut lines, mut commands): (Vec<usize>, Vec<Command>) =
+            parser::parse_commands(source, options, &mut warnings)?
+                .into_iter()
+                .unzip();
 
         let mut catalog = None;
+        let mut cdtextfile = None;
         let mut performer = None;
         let mut title = None;
+        let mut songwriter = None;
         let mut genre = None;
         let mut date = None;
         let mut discid = None;
         let mut comment = None;
         let mut discnumber = None;
         let mut totaldiscs = None;
+        let mut encoding = None;
+        let mut rating = None;
+        let mut leadout: Option<Time> = None;
+        let mut rem = HashMap::new();
+        let mut seen_rems: HashMap<String, String> = HashMap::new();
 
         while commands.len() > 0 {
+            let line = lines[lines.len() - commands.len()];
             match commands[0].clone() {
                 Command::Catalog(p) => {
                     catalog = Some(p);
                     commands.remove(0);
                 }
+                Command::Cdtextfile(f) => {
+                    cdtextfile = Some(f);
+                    commands.remove(0);
+                }
                 Command::Performer(p) => {
                     performer = Some(p);
                     commands.remove(0);
@@ -85,12 +393,35 @@ impl Tracklist {
                     title = Some(t);
                     commands.remove(0);
                 }
+                Command::Songwriter(s) => {
+                    songwriter = Some(s);
+                    commands.remove(0);
+                }
+                Command::Isrc(_) => {
+                    warnings.push(format!("line {}: Ignoring ISRC outside of a track.", line));
+                    commands.remove(0);
+                }
                 Command::Rem(t, d) => {
-                    match t.to_uppercase().as_str() {
+                    let key = t.to_uppercase();
+                    if let Some(first) = seen_rems.get(&key) {
+                        // Only the first value of a repeated REM is kept.
+                        if *first != d {
+                            warnings.push(format!(
+                                "line {}: Conflicting REM {} values {:?} and {:?}, keeping the first.",
+                                line, key, first, d
+                            ));
+                        }
+                        commands.remove(0);
+                        continue;
+                    }
+                    seen_rems.insert(key.clone(), d.clone());
+
+                    match key.as_str() {
                       "GENRE" => genre = Some(d),
                       "DATE" => date = Some(d),
                       "DISCID" => discid = Some(d),
                       "COMMENT" => comment = Some(d),
+                      "ENCODING" => encoding = Some(d),
                       "DISCNUMBER" => {
                         if let Ok(x) = d.parse() {
                           discnumber = Some(x);
@@ -101,7 +432,25 @@ impl Tracklist {
                           totaldiscs = Some(x);
                         }
                       },
-                      _ => (),
+                      "LEAD-OUT" | "LEADOUT" => {
+                        match d.parse() {
+                          Ok(x) => leadout = Some(x),
+                          Err(_) => {
+                            rem.insert(key.to_string(), d);
+                          }
+                        }
+                      },
+                      "RATING" => {
+                        match d.parse() {
+                          Ok(x) => rating = Some(x),
+                          Err(_) => {
+                            rem.insert("RATING".to_string(), d);
+                          }
+                        }
+                      },
+                      key => {
+                        rem.insert(key.to_string(), d);
+                      },
                     }
                     commands.remove(0);
                 }
@@ -111,372 +460,3629 @@ impl Tracklist {
             }
         }
 
+        if let Some(&Command::Track(..)) = commands.first() {
+            // Some tools omit the FILE line when the cue sheet describes a single image.
+            let position = lines.len() - commands.len();
+            let line = lines[position];
+            if options.strict {
+                return Err(Error::UnexpectedCommand {
+                    line,
+                    command: format!("{:?}", commands[0]),
+                });
+            }
+            let message = "No FILE before the first TRACK, assuming a single file.";
+            warnings.push(format!("line {}: {}", line, message));
+            lines.insert(position, line);
+            commands.insert(0, Command::File(String::new(), FileFormat::Wave));
+        }
+
         let mut files = Vec::new();
-        while commands.len() > 0 {
-            if let Ok(file) = TrackFile::consume(&mut commands) {
-                files.push(file);
-            } else {
-                break;
+        while let Some(&Command::File(..)) = commands.first() {
+            files.push(TrackFile::consume(
+                &mut commands,
+                &lines,
+                &mut warnings,
+                callback,
+            )?);
+        }
+
+        if let Some(command) = commands.first() {
+            let line = lines[lines.len() - commands.len()];
+            if options.strict {
+                return Err(Error::UnexpectedCommand {
+                    line,
+                    command: format!("{:?}", command),
+                });
+            }
+            warnings.push(format!(
+                "line {}: Ignoring the rest of the cue sheet from unexpected command {:?}.",
+                line, command
+            ));
+        }
+
+        if let Some(ref leadout) = leadout {
+            if let Some(track) = files.last_mut().and_then(|f| f.tracks.last_mut()) {
+                if let Some(start) = track.index_time(1).cloned() {
+                    track.duration = leadout.checked_sub(&start);
+                }
             }
         }
 
         Ok(Tracklist {
             catalog,
             files,
+            cdtextfile,
             performer,
             title,
+            songwriter,
             genre,
             date,
             discid,
             comment,
             discnumber,
             totaldiscs,
+            encoding,
+            rating,
+            leadout,
+            rem,
+            warnings,
         })
     }
-}
 
-/// One file described by a tracklist.
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct TrackFile {
-    /// List of tracks contained in the file.
-    pub tracks: Vec<Track>,
+    /// Returns the tracklist with its title set to `title`.
+    pub fn with_title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = Some(title.into());
+        self
+    }
 
-    /// The filename.
-    pub name: String,
+    /// Returns the tracklist with its performer set to `performer`.
+    pub fn with_performer<S: Into<String>>(mut self, performer: S) -> Self {
+        self.performer = Some(performer.into());
+        self
+    }
 
-    /// The format of the file.
-    pub format: FileFormat,
-}
+    /// Returns the tracklist with its date set to `date`.
+    pub fn with_date<S: Into<String>>(mut self, date: S) -> Self {
+        self.date = Some(date.into());
+        self
+    }
 
-impl TrackFile {
-    fn consume(commands: &mut Vec<Command>) -> Result<Self, Error> {
-        if let Command::File(name, format) = commands.remove(0) {
-            let mut tracks: Vec<Track> = Vec::new();
-            let mut last_time: Option<Time> = None;
+    /// Returns the year of the tracklist, extracted from `REM DATE`.
+    ///
+    /// The date is scanned for the first sequence of four consecutive digits in the range
+    /// 1900–2099, so values like `1985`, `1985-10-14` or `Recorded 1985` all work.
+    pub fn year(&self) -> Option<u16> {
+        let date = self.date.as_ref()?;
+        let bytes = date.as_bytes();
 
-            while commands.len() > 0 {
-                if let Ok(track) = Track::consume(commands) {
-                    if track.index.len() > 0 {
-                        // INDEX 01 is required and the start of the track
-                        let time = track.index.clone().into_iter().find(|a| a.0 == 1).unwrap();
-
-                        if let Some(start) = last_time {
-                            let stop = track.index[0].clone().1;
-                            let duration = stop - start;
-
-                            let track_n = tracks.len();
-                            if let Some(last_track) = tracks.get_mut(track_n - 1) {
-                                (*last_track).duration = Some(duration);
-                            }
-                        }
+        bytes
+            .windows(4)
+            .filter(|w| w.iter().all(|b| b.is_ascii_digit()))
+            .filter_map(|w| ::std::str::from_utf8(w).ok()?.parse().ok())
+            .find(|year| (1900..2100).contains(year))
+    }
 
-                        last_time = Some(time.1);
-                    } else {
-                        last_time = None;
-                    }
+    /// Returns the position in `files` of the file containing the track with the given number.
+    pub fn file_index_of_track(&self, number: u32) -> Option<usize> {
+        self.files
+            .iter()
+            .position(|file| file.tracks.iter().any(|track| track.number == number))
+    }
 
-                    tracks.push(track);
-                } else {
-                    break;
-                }
-            }
-            Ok(TrackFile {
-                tracks,
-                name,
-                format,
-            })
-        } else {
-            Err("TrackFile::consume called but no Track command found.".into())
+    /// Returns the total number of index points of all tracks in the tracklist.
+    pub fn index_point_count(&self) -> usize {
+        self.tracks().map(|track| track.index.len()).sum()
+    }
+
+    /// Returns the share of the total disc time taken by each track, as `(number, fraction)`.
+    ///
+    /// This requires the duration of every track to be known, otherwise an empty `Vec` is
+    /// returned. The fractions sum up to 1.
+    pub fn track_time_shares(&self) -> Vec<(u32, f64)> {
+        let tracks: Vec<&Track> = self.tracks().collect();
+        if tracks.iter().any(|track| track.duration.is_none()) {
+            return Vec::new();
+        }
+
+        let total: f64 = tracks
+            .iter()
+            .filter_map(|track| track.duration.as_ref())
+            .map(Time::total_seconds)
+            .sum();
+        if total <= 0. {
+            return Vec::new();
         }
+
+        tracks
+            .iter()
+            .filter_map(|track| {
+                let duration = track.duration.as_ref()?;
+                Some((track.number, duration.total_seconds() / total))
+            })
+            .collect()
     }
-}
 
-/// One track described by a tracklist.
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct Track {
-    /// Title of the track.
-    pub title: Option<String>,
+    /// Returns an iterator over all tracks of all files, in order.
+    pub fn tracks(&self) -> impl Iterator<Item = &Track> {
+        self.files.iter().flat_map(|f| f.tracks.iter())
+    }
 
-    /// Type of the track.
-    pub track_type: TrackType,
+    /// Returns a mutable iterator over all tracks of all files, in order.
+    pub fn tracks_mut(&mut self) -> impl Iterator<Item = &mut Track> {
+        self.files.iter_mut().flat_map(|f| f.tracks.iter_mut())
+    }
 
-    /// Duration of the track, if it was possible to determine it.
+    /// Returns the sum of the durations of all tracks.
     ///
-    /// This is only possible if tracks have index commands attached to them.
-    /// Also note that with just a cue file it is usually not possible to determine the duration of
-    /// the last track in the list.
-    pub duration: Option<Time>,
+    /// If the duration of any track is unknown, which usually is the case for the last one, `None`
+    /// is returned.
+    pub fn total_duration(&self) -> Option<Time> {
+        self.tracks().try_fold(Time::new(0, 0, 0), |total, track| {
+            Some(total + track.duration.clone()?)
+        })
+    }
 
-    /// Index commands attached to this track (if any).
-    pub index: Vec<Index>,
+    /// Returns true if both tracklists describe the same disc.
+    ///
+    /// The catalog, the disc ID, the number of tracks and the start of every track (with a
+    /// tolerance of a few frames) are compared, while titles, performers and the like are ignored.
+    /// This way two differently tagged rips of a disc are recognized.
+    pub fn same_disc(&self, other: &Tracklist) -> bool {
+        let tracks: Vec<&Track> = self.tracks().collect();
+        let other_tracks: Vec<&Track> = other.tracks().collect();
 
-    /// Track number as provided in the cue sheet.
-    pub number: u32,
+        self.catalog == other.catalog
+            && self.discid == other.discid
+            && tracks.len() == other_tracks.len()
+            && tracks.iter().zip(other_tracks.iter()).all(|(a, b)| {
+                match (a.index_time(1), b.index_time(1)) {
+                    (Some(a), Some(b)) => a.approx_eq(b.clone(), SAME_DISC_TOLERANCE),
+                    (None, None) => true,
+                    _ => false,
+                }
+            })
+    }
 
-    /// The performer of the track if any was stated.
-    pub performer: Option<String>,
+    /// Returns the titles of all tracks in order, skipping untitled tracks and repeated titles.
+    pub fn titles(&self) -> Vec<String> {
+        let mut titles: Vec<String> = Vec::new();
+        for track in self.tracks() {
+            if let Some(ref title) = track.title {
+                if !titles.contains(title) {
+                    titles.push(title.clone());
+                }
+            }
+        }
+        titles
+    }
 
-    /// International Standard Recording Code of this track
-    pub isrc: Option<String>,
-}
+    /// Normalizes every index time of the tracklist with `Time::normalized`.
+    ///
+    /// This repairs out of range seconds and frames accepted by lenient parsing before the
+    /// tracklist is written back to a cue sheet.
+    pub fn normalize_times(&mut self) {
+        for file in &mut self.files {
+            for track in &mut file.tracks {
+                for index in &mut track.index {
+                    index.1 = index.1.normalized();
+                }
+            }
+        }
+    }
 
-type Index = (u32, Time);
+    /// Returns a copy of the tracklist with all index times shifted by the 150 frames (two
+    /// seconds) lead-in which precede track 1 on a CD.
+    ///
+    /// The times of the copy are absolute disc positions as used by tables of contents and disc
+    /// IDs. With multiple files the times of every file are also shifted by the length of the
+    /// files before it, like in `cumulative_starts`, and so is the `leadout` of the last file.
+    /// Returns `None` if the position of a file can't be determined.
+    pub fn with_leadin(&self) -> Option<Tracklist> {
+        let offsets = self.file_offsets();
+        if offsets.len() < self.files.len() {
+            return None;
+        }
 
-impl Track {
-    fn consume(commands: &mut Vec<Command>) -> Result<Track, Error> {
-        if let Command::Track(number, track_type) = commands.remove(0) {
-            let mut title = None;
-            let mut performer = None;
-            let mut isrc = None;
-            let mut index = Vec::new();
+        let shift =
+            |time: &Time, offset: i64| Time::from_frames(time.total_frames() + offset + 150);
+        let mut tracklist = self.clone();
+        for (file, &offset) in tracklist.files.iter_mut().zip(&offsets) {
+            for track in &mut file.tracks {
+                for index in &mut track.index {
+                    index.1 = shift(&index.1, offset);
+                }
+            }
+        }
+        let last = offsets.last().cloned().unwrap_or(0);
+        tracklist.leadout = self.leadout.as_ref().map(|leadout| shift(leadout, last));
+        Some(tracklist)
+    }
 
-            while commands.len() > 0 {
-                match commands[0].clone() {
-                    Command::Performer(p) => {
-                        performer = Some(p);
-                        commands.remove(0);
-                    }
-                    Command::Title(t) => {
-                        title = Some(t);
-                        commands.remove(0);
-                    }
-                    Command::Isrc(t) => {
-                        isrc = Some(t);
-                        commands.remove(0);
-                    }
-                    Command::Pregap(time) => {
-                        let next_command = commands
-                            .get(1)
-                            .ok_or("Pregap is the last command in the track!".to_owned())?
-                            .to_owned();
+    /// Returns the `(track number, index number)` of every index whose frame component is 75 or
+    /// more, which is invalid on a CD.
+    ///
+    /// Such times are only accepted when parsing with a higher `ParseOptions::frames_per_second`.
+    pub fn invalid_frames(&self) -> Vec<(u32, u32)> {
+        self.tracks()
+            .flat_map(|track| {
+                track
+                    .index
+                    .iter()
+                    .filter(|index| index.1.frames() as i64 >= FPS)
+                    .map(move |index| (track.number, index.0))
+            })
+            .collect()
+    }
 
-                        let first_index;
-                        match next_command {
-                            Command::Index(_, time) => first_index = time,
-                            _ => {
-                                return Err("Pregap is not followed by an index!".into());
-                            }
-                        }
-                        let diff = first_index.total_frames() - time.total_frames();
-                        index.push((0, Time::from_frames(diff)));
-                        commands.remove(0);
-                    }
-                    Command::Index(i, time) => {
-                        index.push((i, time));
-                        commands.remove(0);
+    /// Returns the numbers of all tracks whose `INDEX 00` lies before the `INDEX 01` of the
+    /// previous track in the same file.
+    ///
+    /// The pregap of a track is taken from the end of the previous track, so it can't start
+    /// before that track does. Such cue sheets assign the gap to the wrong track.
+    pub fn pregap_violations(&self) -> Vec<u32> {
+        let mut violations = Vec::new();
+        for file in &self.files {
+            for pair in file.tracks.windows(2) {
+                let previous = pair[0].index_time(1);
+                let pregap = pair[1].index_time(0);
+                if let (Some(previous), Some(pregap)) = (previous, pregap) {
+                    if pregap < previous {
+                        violations.push(pair[1].number);
                     }
-                    _ => break,
                 }
             }
+        }
+        violations
+    }
 
-            Ok(Track {
-                title,
-                track_type,
-                duration: None,
-                index,
-                number,
-                performer,
-                isrc,
+    /// Produces a human readable report of the tracklist, similar to the output of `cueprint`.
+    ///
+    /// The report lists album, performer and number of tracks, followed by one line per track
+    /// with its number, title, performer (falling back to the album performer) and duration.
+    pub fn to_report(&self) -> String {
+        let tracks: Vec<&Track> = self.tracks().collect();
+        let album_performer = self.performer.as_deref().unwrap_or("[No Artist]");
+
+        let mut report = String::new();
+        report.push_str(&format!(
+            "Album:     {}\n",
+            self.title.as_deref().unwrap_or("[No Album]")
+        ));
+        report.push_str(&format!("Performer: {}\n", album_performer));
+        report.push_str(&format!("Tracks:    {}\n", tracks.len()));
+        report.push('\n');
+
+        for track in tracks {
+            let duration = match track.duration {
+                Some(ref time) => time.to_string_2(),
+                None => "??:??".to_string(),
+            };
+            report.push_str(&format!(
+                "{:02}. {} - {} ({})\n",
+                track.number,
+                track.title.as_deref().unwrap_or("[No Title]"),
+                track.performer.as_deref().unwrap_or(album_performer),
+                duration
+            ));
+        }
+
+        report
+    }
+
+    /// Produces an extended M3U playlist with one entry per track.
+    ///
+    /// Each entry has an `#EXTINF` line with the duration rounded to whole seconds, or `-1` if it
+    /// is unknown, and `Performer - Title`, followed by the name of the file containing the
+    /// track. Tracks sharing a file all reference that file, tracks of an implicit file (one
+    /// without a name, as the cue sheet had no FILE line) are left out.
+    pub fn to_m3u(&self) -> String {
+        let mut m3u = String::from("#EXTM3U\n");
+        for file in self.files.iter().filter(|file| !file.name.is_empty()) {
+            for track in &file.tracks {
+                let seconds = match track.duration {
+                    Some(ref time) => time.total_seconds().round() as i64,
+                    None => -1,
+                };
+                let title = match track.title {
+                    Some(ref title) => title.clone(),
+                    None => format!("Track {:02}", track.number),
+                };
+                let name = match track.performer.as_ref().or(self.performer.as_ref()) {
+                    Some(performer) => format!("{} - {}", performer, title),
+                    None => title,
+                };
+                m3u.push_str(&format!("#EXTINF:{},{}\n{}\n", seconds, name, file.name));
+            }
+        }
+        m3u
+    }
+
+    /// Checks that track 1 starts at the beginning of the disc, i.e. that it has an `INDEX 01`
+    /// and that either it or the `INDEX 00` is at `00:00:00`.
+    ///
+    /// A missing track 1 or a late start of it is a strong sign of a corrupted cue sheet.
+    pub fn track_one_ok(&self) -> bool {
+        let track = self.tracks().find(|track| track.number == 1);
+        let track = match track {
+            Some(track) => track,
+            None => return false,
+        };
+
+        let at_zero = |number| match track.index_time(number) {
+            Some(time) => time.total_frames() == 0,
+            None => false,
+        };
+        track.index_time(1).is_some() && (at_zero(0) || at_zero(1))
+    }
+
+    /// Returns the title to display for `track`.
+    ///
+    /// Unlike performers, titles are not inherited: the title of the disc is the album name and
+    /// doesn't describe a single track. So this is the title of the track itself, or `None`.
+    pub fn title_for_track<'a>(&self, track: &'a Track) -> Option<&'a str> {
+        track.title.as_deref()
+    }
+
+    /// Returns the average gap between `INDEX 00` and `INDEX 01` of all tracks having an
+    /// `INDEX 00`, rounded down to whole frames.
+    ///
+    /// Returns `None` if no track has a pregap.
+    pub fn average_pregap(&self) -> Option<Time> {
+        let gaps: Vec<i64> = self
+            .tracks()
+            .filter_map(|track| {
+                let pregap = track.index_time(0)?;
+                let start = track.index_time(1)?;
+                Some(start.total_frames() - pregap.total_frames())
             })
+            .collect();
+
+        if gaps.is_empty() {
+            None
         } else {
-            Err("Track::consume called but no Track command found.".into())
+            let sum: i64 = gaps.iter().sum();
+            Some(Time::from_frames(sum / gaps.len() as i64))
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Returns whether the disc starts with a hidden track (HTOA), i.e. the first track has an
+    /// `INDEX 00` at `00:00:00` and its `INDEX 01` only later.
+    pub fn has_hidden_track(&self) -> bool {
+        let first = self.files.first().and_then(|f| f.tracks.first());
+        match first.map(|track| (track.index_time(0), track.index_time(1))) {
+            Some((Some(pregap), Some(start))) => pregap.total_frames() == 0 && *start > *pregap,
+            _ => false,
+        }
+    }
 
-    #[test]
-    fn sample() {
-        let source = r#"REM GENRE "Progressive Rock"
-REM DATE 1985
-REM DISCID DC0E6811
-REM COMMENT "ExactAudioCopy v0.95b3"
-REM DISCNUMBER 2
-REM TOTALDISCS 2
-CATALOG 0724349703629
+    /// Extracts the file at `index` into a standalone single-file tracklist.
+    ///
+    /// The tracks of the file are renumbered starting at 1 and all index times are shifted so the
+    /// `INDEX 01` of the first track is at `00:00:00`, earlier indices are clamped to `00:00:00`.
+    /// The disc metadata is copied over, except for the lead-out which is only kept, shifted like
+    /// the indices, for the last file. Returns `None` if there is no file at `index`.
+    pub fn extract_file(&self, index: usize) -> Option<Tracklist> {
+        let mut file = self.files.get(index)?.clone();
+        let base = file
+            .tracks
+            .first()
+            .and_then(|track| track.index_time(1))
+            .map_or(0, Time::total_frames);
+
+        for (i, track) in file.tracks.iter_mut().enumerate() {
+            track.number = i as u32 + 1;
+            for index in &mut track.index {
+                index.1 = Time::from_frames((index.1.total_frames() - base).max(0));
+            }
+        }
+
+        let mut tracklist = self.clone();
+        // The lead-out belongs to the last file, so it only carries over from that one.
+        tracklist.leadout = match self.leadout {
+            Some(ref leadout) if index + 1 == self.files.len() => {
+                Some(Time::from_frames((leadout.total_frames() - base).max(0)))
+            }
+            _ => None,
+        };
+        tracklist.files = vec![file];
+        tracklist.warnings = Vec::new();
+        Some(tracklist)
+    }
+
+    /// Numbers all tracks sequentially starting at 1, e.g. after inserting or removing tracks.
+    ///
+    /// Numbering continues across files as usual for discs split into one file per track, so the
+    /// first track of the second file follows the last track of the first one.
+    pub fn renumber(&mut self) {
+        for (number, track) in (1..).zip(self.tracks_mut()) {
+            track.number = number;
+        }
+    }
+
+    /// Returns all tracks sorted by duration, longest first.
+    ///
+    /// Tracks with an unknown duration are placed last, tracks of equal duration keep their order.
+    pub fn tracks_by_duration_desc(&self) -> Vec<&Track> {
+        let mut tracks: Vec<&Track> = self.tracks().collect();
+        // `None` is smaller than any `Some`, so unknown durations end up last.
+        tracks.sort_by(|a, b| b.duration.cmp(&a.duration));
+        tracks
+    }
+
+    /// Returns the numbers of all tracks with a known duration shorter than `threshold`.
+    ///
+    /// Very short tracks are usually the result of a misplaced index.
+    pub fn short_tracks(&self, threshold: Time) -> Vec<u32> {
+        self.tracks()
+            .filter(|track| match track.duration {
+                Some(ref duration) => *duration < threshold,
+                None => false,
+            })
+            .map(|track| track.number)
+            .collect()
+    }
+
+    /// Returns the numbers of all tracks without an ISRC.
+    pub fn tracks_without_isrc(&self) -> Vec<u32> {
+        self.tracks()
+            .filter(|track| track.isrc.is_none())
+            .map(|track| track.number)
+            .collect()
+    }
+
+    /// Returns the first track with the given ISRC, ignoring case.
+    ///
+    /// ISRCs should be unique on a disc, this isn't enforced though, see `validation_report`.
+    pub fn track_by_isrc(&self, isrc: &str) -> Option<&Track> {
+        self.tracks().find(|track| match track.isrc {
+            Some(ref code) => code.eq_ignore_ascii_case(isrc),
+            None => false,
+        })
+    }
+
+    /// Returns the distinct formats of the files of the tracklist, in order of first appearance.
+    pub fn formats_used(&self) -> Vec<FileFormat> {
+        let mut formats = Vec::new();
+        for file in &self.files {
+            if !formats.contains(&file.format) {
+                formats.push(file.format.clone());
+            }
+        }
+        formats
+    }
+
+    /// Runs all quality checks on the tracklist, returning every error found.
+    ///
+    /// Errors are a catalog number which isn't 13 digits, malformed ISRCs, track numbers not
+    /// counting up from 1, tracks without `INDEX 01`, indices going backwards, frames out of range
+    /// and tracks overlapping the previous one. Use `validation_report` to also get the warnings.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let report = self.validation_report();
+        if report.is_ok() {
+            Ok(())
+        } else {
+            Err(report.errors)
+        }
+    }
+
+    /// Runs all quality checks on the tracklist like `validate`, also collecting warnings.
+    ///
+    /// Duplicate ISRCs and an unknown duration of the last track are reported as warnings.
+    pub fn validation_report(&self) -> ValidationReport {
+        validation::validate(self)
+    }
+
+    /// Checks that every file referenced by the tracklist exists relative to `base`, is a regular
+    /// file and can be opened for reading.
+    ///
+    /// Returns the names of all files failing the check, an empty `Vec` means all are fine. An
+    /// implicit file (one without a name) isn't checked.
+    pub fn validate_files(&self, base: &Path) -> Vec<String> {
+        let readable = |path: &Path| -> io::Result<bool> {
+            File::open(path)?;
+            Ok(fs::metadata(path)?.is_file())
+        };
+        self.files
+            .iter()
+            .filter(|file| !file.name.is_empty())
+            .filter(|file| !readable(&base.join(&file.name)).unwrap_or(false))
+            .map(|file| file.name.clone())
+            .collect()
+    }
+
+    /// Returns the absolute start (`INDEX 01`) of every track across the whole disc, in order.
+    ///
+    /// For a single file these are the times stored in the cue sheet. With multiple files the
+    /// length of every file is added to the starts of the tracks in the files after it, where the
+    /// length of a file is the end of its last track. If that isn't known, the starts of the
+    /// following files can't be determined and are left out. Tracks without `INDEX 01` are skipped.
+    pub fn cumulative_starts(&self) -> Vec<Time> {
+        let mut starts = Vec::new();
+        for (file, offset) in self.files.iter().zip(self.file_offsets()) {
+            for track in &file.tracks {
+                if let Some(start) = track.index_time(1) {
+                    starts.push(Time::from_frames(offset + start.total_frames()));
+                }
+            }
+        }
+        starts
+    }
+
+    /// Returns the offset in frames of every file from the start of the disc, stopping at the
+    /// first file whose offset can't be determined.
+    fn file_offsets(&self) -> Vec<i64> {
+        let mut offsets = Vec::new();
+        let mut offset = 0;
+
+        for file in &self.files {
+            offsets.push(offset);
+            let end = file.tracks.last().and_then(|track| {
+                let start = track.index_time(1)?;
+                let duration = track.duration.as_ref()?;
+                Some(start.total_frames() + duration.total_frames())
+            });
+            match end {
+                Some(end) => offset += end,
+                None => break,
+            }
+        }
+
+        offsets
+    }
+
+    /// Returns the position of the lead-out as an absolute MSF address, i.e. including the 150
+    /// frames (two seconds) lead-in which precede track 1 on a CD.
+    ///
+    /// This is the end of the last track, so the duration of the last track has to be known, for
+    /// instance by setting it from the length of the audio file. Returns `None` otherwise.
+    pub fn leadout_msf(&self) -> Option<Time> {
+        self.total_frames()
+            .map(|total| Time::from_frames(total + 150))
+    }
+
+    /// Returns the percentage of a disc with a capacity of `capacity_minutes` which is used by
+    /// the tracklist.
+    ///
+    /// Like for `leadout_msf` the duration of the last track has to be known.
+    pub fn fill_percent(&self, capacity_minutes: u32) -> Option<f64> {
+        let capacity = i64::from(capacity_minutes) * 60 * FPS;
+        self.total_frames()
+            .map(|total| total as f64 / capacity as f64 * 100.)
+    }
+
+    /// Returns the length of the whole tracklist in frames, which is the end of its last track.
+    fn total_frames(&self) -> Option<i64> {
+        let file = self.files.last()?;
+        let offset = *self.file_offsets().get(self.files.len() - 1)?;
+        let track = file.tracks.last()?;
+        let start = track.index_time(1)?.total_frames();
+        let duration = track.duration.as_ref()?.total_frames();
+        Some(offset + start + duration)
+    }
+
+    /// Returns a chapter list pairing the absolute start of every track with its title.
+    ///
+    /// This is the format expected by common tagging crates for chapter markers, e.g. for m4b
+    /// audiobooks. Untitled tracks are named after their number. The starts are computed like in
+    /// `cumulative_starts`, so tracks left out there are missing here as well.
+    pub fn to_chapters(&self) -> Vec<(Duration, String)> {
+        let tracks = self.tracks().filter(|track| track.index_time(1).is_some());
+
+        self.cumulative_starts()
+            .iter()
+            .zip(tracks)
+            .map(|(start, track)| {
+                let title = match track.title {
+                    Some(ref title) => title.clone(),
+                    None => format!("Track {:02}", track.number),
+                };
+                (start.as_duration(), title)
+            })
+            .collect()
+    }
+
+    /// Produces an ffmpeg metadata file (`;FFMETADATA1`) with one chapter per track.
+    ///
+    /// Chapters use a timebase of one frame (`1/75`) and the absolute starts of
+    /// `cumulative_starts`. A chapter ends where the next one starts, the last one at the end of
+    /// the last track, which a `REM LEADOUT` determines when parsing. If that isn't known, `END`
+    /// is left out of the last chapter.
+    pub fn to_ffmetadata(&self) -> String {
+        let mut metadata = String::from(";FFMETADATA1\n");
+        if let Some(ref title) = self.title {
+            metadata.push_str(&format!("title={}\n", escape_ffmetadata(title)));
+        }
+        if let Some(ref performer) = self.performer {
+            metadata.push_str(&format!("artist={}\n", escape_ffmetadata(performer)));
+        }
+
+        let starts: Vec<i64> = self
+            .cumulative_starts()
+            .iter()
+            .map(Time::total_frames)
+            .collect();
+        let last_end = self.total_frames();
+        for (i, (_, title)) in self.to_chapters().into_iter().enumerate() {
+            metadata.push_str(&format!("\n[CHAPTER]\nTIMEBASE=1/{}\n", FPS));
+            metadata.push_str(&format!("START={}\n", starts[i]));
+            if let Some(end) = starts.get(i + 1).cloned().or(last_end) {
+                metadata.push_str(&format!("END={}\n", end));
+            }
+            metadata.push_str(&format!("title={}\n", escape_ffmetadata(&title)));
+        }
+        metadata
+    }
+
+    /// Returns a suggested file name and the tags for every track, for splitting the audio into
+    /// one file per track and tagging those.
+    ///
+    /// The file names look like `01 - Lady Nina.flac`, keeping the extension of the file the
+    /// track is in (`wav` for an implicit file), with path separators in titles replaced by `_`. The tags are those of
+    /// `Track::as_tags` completed with the disc metadata: `ALBUM`, `GENRE`, `DISCNUMBER`,
+    /// `TOTALDISCS` and, unless the track has its own, `ARTIST` and `DATE`.
+    pub fn to_tag_sidecars(&self) -> Vec<(String, BTreeMap<String, String>)> {
+        let mut sidecars = Vec::new();
+        for file in &self.files {
+            let extension = Path::new(&file.name)
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .unwrap_or("wav");
+
+            for track in &file.tracks {
+                let name = match track.title {
+                    Some(ref title) => format!(
+                        "{:02} - {}.{}",
+                        track.number,
+                        title.replace(['/', '\\'], "_"),
+                        extension
+                    ),
+                    None => format!("{:02}.{}", track.number, extension),
+                };
+
+                let mut tags = track.as_tags();
+                let disc = [
+                    ("ALBUM", self.title.clone()),
+                    ("ARTIST", self.performer.clone()),
+                    ("DATE", self.date.clone()),
+                    ("GENRE", self.genre.clone()),
+                    ("DISCNUMBER", self.discnumber.map(|n| n.to_string())),
+                    ("TOTALDISCS", self.totaldiscs.map(|n| n.to_string())),
+                ];
+                for &(key, ref value) in disc.iter() {
+                    if let Some(ref value) = *value {
+                        tags.entry(key.to_string()).or_insert_with(|| value.clone());
+                    }
+                }
+                sidecars.push((name, tags));
+            }
+        }
+        sidecars
+    }
+
+    /// Serializes the tracklist back into the text of a cue sheet.
+    pub fn to_cue_string(&self) -> String {
+        self.to_cue_string_with_options(&EmitOptions::default())
+    }
+
+    /// Serializes the tracklist back into the text of a cue sheet using the specified `options`.
+    pub fn to_cue_string_with_options(&self, options: &EmitOptions) -> String {
+        let mut out = String::new();
+        self.write_cue(&mut out, options)
+            .expect("Writing to a String can't fail.");
+        out
+    }
+
+    /// Writes the cue sheet text of the tracklist, as produced by `Display`, into `w`.
+    ///
+    /// The text is streamed into `w` piece by piece instead of being built in memory first.
+    pub fn write_to<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        write!(w, "{}", self)
+    }
+
+    /// Serializes the tracklist into a cue sheet suitable for embedding into the audio file
+    /// itself, e.g. as the `CUESHEET` Vorbis comment of a FLAC or WavPack image.
+    ///
+    /// The embedded cue sheet describes a single file: the tracks of all files are merged into
+    /// the first file, keeping its name, with their index times shifted to be absolute from the
+    /// start of the disc. Files whose offset can't be determined (see `cumulative_starts`) are
+    /// left out. The result can be read back with `parse_embedded`.
+    pub fn to_embedded_cuesheet(&self) -> String {
+        let mut tracks = Vec::new();
+        for (file, offset) in self.files.iter().zip(self.file_offsets()) {
+            for track in &file.tracks {
+                let mut track = track.clone();
+                for index in &mut track.index {
+                    index.1 = Time::from_frames(offset + index.1.total_frames());
+                }
+                tracks.push(track);
+            }
+        }
+
+        let mut tracklist = self.clone();
+        tracklist.files = self
+            .files
+            .first()
+            .map(|first| TrackFile {
+                tracks,
+                name: first.name.clone(),
+                format: first.format.clone(),
+            })
+            .into_iter()
+            .collect();
+        tracklist.to_cue_string()
+    }
+
+    /// Converts the tracklist into the `.toc` format of cdrdao.
+    ///
+    /// Every track gets a `FILE` statement with its start in the audio file and, if known, its
+    /// length. The pregap between `INDEX 00` and `INDEX 01` is part of the track data and marked
+    /// with `START`, further indices are given relative to `INDEX 01`. Tracks without `INDEX 01`
+    /// are left out. Tracks with flags get `COPY`, `PRE_EMPHASIS` and `FOUR_CHANNEL_AUDIO`
+    /// statements, written in their `NO` or `TWO_CHANNEL_AUDIO` form for absent flags.
+    ///
+    /// Tracks of an implicit file (one without a name) have no audio file to reference, so their
+    /// `FILE`, `START` and `INDEX` statements are left out.
+    pub fn to_toc(&self) -> String {
+        let tracks = || self.tracks();
+        let header = if tracks().all(|track| track.track_type == TrackType::Audio) {
+            "CD_DA"
+        } else if tracks().any(|track| toc_mode(&track.track_type).starts_with("MODE2")) {
+            "CD_ROM_XA"
+        } else {
+            "CD_ROM"
+        };
+
+        let mut out = String::new();
+        out.push_str(header);
+        out.push('\n');
+        if let Some(ref catalog) = self.catalog {
+            out.push_str(&format!("CATALOG {}\n", toc_quote(catalog)));
+        }
+
+        for file in &self.files {
+            for track in &file.tracks {
+                let start = match track.index_time(1) {
+                    Some(start) => start.total_frames(),
+                    None => continue,
+                };
+                let data_start = track.index_time(0).map_or(start, Time::total_frames);
+
+                out.push_str(&format!("\nTRACK {}\n", toc_mode(&track.track_type)));
+                if !track.flags.is_empty() {
+                    let has = |flag| track.flags.contains(&flag);
+                    let no = |flag| if has(flag) { "" } else { "NO " };
+                    out.push_str(&format!("{}COPY\n", no(TrackFlag::Dcp)));
+                    out.push_str(&format!("{}PRE_EMPHASIS\n", no(TrackFlag::Pre)));
+                    if has(TrackFlag::FourChannel) {
+                        out.push_str("FOUR_CHANNEL_AUDIO\n");
+                    } else {
+                        out.push_str("TWO_CHANNEL_AUDIO\n");
+                    }
+                }
+                if let Some(ref isrc) = track.isrc {
+                    out.push_str(&format!("ISRC {}\n", toc_quote(isrc)));
+                }
+                if !file.name.is_empty() {
+                    out.push_str(&format!(
+                        "FILE {} {}",
+                        toc_quote(&file.name),
+                        Time::from_frames(data_start)
+                    ));
+                    if let Some(ref duration) = track.duration {
+                        let length = start - data_start + duration.total_frames();
+                        out.push_str(&format!(" {}", Time::from_frames(length)));
+                    }
+                    out.push('\n');
+                    if start > data_start {
+                        out.push_str(&format!(
+                            "START {}\n",
+                            Time::from_frames(start - data_start)
+                        ));
+                    }
+                    for &(number, ref time) in &track.index {
+                        if number > 1 {
+                            let offset = time.total_frames() - start;
+                            out.push_str(&format!("INDEX {}\n", Time::from_frames(offset)));
+                        }
+                    }
+                }
+            }
+        }
+
+        out
+    }
+
+    fn write_cue<W: fmt::Write>(&self, w: &mut W, options: &EmitOptions) -> fmt::Result {
+        let newline = options.line_ending.as_str();
+        let rems = [
+            ("GENRE", self.genre.clone()),
+            ("DATE", self.date.clone()),
+            ("DISCID", self.discid.clone()),
+            ("COMMENT", self.comment.clone()),
+            ("DISCNUMBER", self.discnumber.map(|n| n.to_string())),
+            ("TOTALDISCS", self.totaldiscs.map(|n| n.to_string())),
+            ("ENCODING", self.encoding.clone()),
+            ("RATING", self.rating.map(|n| n.to_string())),
+        ];
+        for &(key, ref value) in rems.iter() {
+            if let Some(ref value) = *value {
+                write!(w, "REM {} {}{}", key, format_string(value), newline)?;
+            }
+        }
+        if let Some(ref leadout) = self.leadout {
+            write!(w, "REM LEADOUT {}{}", leadout, newline)?;
+        }
+        let mut rem: Vec<_> = self.rem.iter().collect();
+        rem.sort();
+        for (key, value) in rem {
+            write!(w, "REM {} {}{}", key, format_string(value), newline)?;
+        }
+        if let Some(ref catalog) = self.catalog {
+            if !options.omit_invalid_catalog || is_valid_ean13(catalog) {
+                write!(w, "CATALOG {}{}", format_string(catalog), newline)?;
+            }
+        }
+        if let Some(ref cdtextfile) = self.cdtextfile {
+            write!(w, "CDTEXTFILE {}{}", quote_string(cdtextfile), newline)?;
+        }
+        if let Some(ref performer) = self.performer {
+            write!(w, "PERFORMER {}{}", quote_string(performer), newline)?;
+        }
+        if let Some(ref title) = self.title {
+            write!(w, "TITLE {}{}", quote_string(title), newline)?;
+        }
+        if let Some(ref songwriter) = self.songwriter {
+            write!(w, "SONGWRITER {}{}", quote_string(songwriter), newline)?;
+        }
+
+        for file in &self.files {
+            // An implicit file (no FILE line in the source) is written back without one.
+            if !file.name.is_empty() {
+                write!(
+                    w,
+                    "FILE {} {}{}",
+                    quote_string(&file.name),
+                    file.format,
+                    newline
+                )?;
+            }
+            for track in &file.tracks {
+                write!(
+                    w,
+                    "  TRACK {:02} {}{}",
+                    track.number, track.track_type, newline
+                )?;
+                if let Some(ref title) = track.title {
+                    write!(w, "    TITLE {}{}", quote_string(title), newline)?;
+                }
+                if let Some(ref performer) = track.performer {
+                    write!(w, "    PERFORMER {}{}", quote_string(performer), newline)?;
+                }
+                if let Some(ref songwriter) = track.songwriter {
+                    write!(w, "    SONGWRITER {}{}", quote_string(songwriter), newline)?;
+                }
+                if !track.flags.is_empty() {
+                    let flags: Vec<String> = track.flags.iter().map(|f| f.to_string()).collect();
+                    write!(w, "    FLAGS {}{}", flags.join(" "), newline)?;
+                }
+                if let Some(ref date) = track.date {
+                    write!(w, "    REM DATE {}{}", format_string(date), newline)?;
+                }
+                if let Some(ref isrc) = track.isrc {
+                    write!(w, "    ISRC {}{}", format_string(isrc), newline)?;
+                }
+                for &(number, ref time) in &track.index {
+                    write!(w, "    INDEX {:02} {}{}", number, time, newline)?;
+                }
+                if let Some(ref postgap) = track.postgap {
+                    write!(w, "    POSTGAP {}{}", postgap, newline)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Computes a fingerprint of the structure of the tracklist.
+    ///
+    /// Only the file names, track numbers, track types and index times are hashed. Metadata like
+    /// titles, performers or REM fields is ignored, so two rips of the same disc which were tagged
+    /// differently will produce the same fingerprint.
+    ///
+    /// The value is the 64 bit FNV-1a hash of a fixed encoding of these fields, so it is stable
+    /// across platforms and versions of Rust and can be persisted, e.g. as a cache key.
+    pub fn fingerprint(&self) -> u64 {
+        // Strings and lists are prefixed with their length, so no two tracklists share an encoding.
+        let mut bytes = Vec::new();
+        let push_str = |bytes: &mut Vec<u8>, value: &str| {
+            bytes.extend_from_slice(&(value.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(value.as_bytes());
+        };
+        for file in &self.files {
+            push_str(&mut bytes, &file.name);
+            bytes.extend_from_slice(&(file.tracks.len() as u64).to_le_bytes());
+            for track in &file.tracks {
+                bytes.extend_from_slice(&track.number.to_le_bytes());
+                push_str(&mut bytes, &track.track_type.to_string());
+                bytes.extend_from_slice(&(track.index.len() as u64).to_le_bytes());
+                for &(number, ref time) in &track.index {
+                    bytes.extend_from_slice(&number.to_le_bytes());
+                    bytes.extend_from_slice(&time.total_frames().to_le_bytes());
+                }
+            }
+        }
+        fnv1a(&bytes)
+    }
+}
+
+impl fmt::Display for Tracklist {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_cue(f, &EmitOptions::default())
+    }
+}
+
+/// Options controlling how a `Tracklist` is serialized into a cue sheet.
+#[derive(Clone, Debug, Default)]
+pub struct EmitOptions {
+    /// Leave out the `CATALOG` line if the catalog is not a valid EAN-13 code.
+    ///
+    /// This prevents propagating broken barcodes into cue sheets used for burning. By default the
+    /// catalog is emitted as is.
+    pub omit_invalid_catalog: bool,
+
+    /// The line ending used to terminate every line.
+    pub line_ending: LineEnding,
+}
+
+/// Line endings which can be used when serializing a cue sheet.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LineEnding {
+    /// A line feed, as used on Unix systems. This is the default.
+    #[default]
+    Lf,
+
+    /// A carriage return followed by a line feed, as required by some Windows burning software.
+    Crlf,
+}
+
+impl LineEnding {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+}
+
+/// Computes the 64 bit FNV-1a hash of `bytes`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Checks that `code` consists of 13 digits with a correct EAN-13 check digit.
+fn is_valid_ean13(code: &str) -> bool {
+    let digits: Vec<u32> = code.chars().filter_map(|c| c.to_digit(10)).collect();
+    if digits.len() != 13 || code.len() != 13 {
+        return false;
+    }
+
+    let sum: u32 = digits[..12]
+        .iter()
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { *d } else { d * 3 })
+        .sum();
+    (10 - sum % 10) % 10 == digits[12]
+}
+
+/// Returns the cdrdao name of the track mode of `track_type`.
+fn toc_mode(track_type: &TrackType) -> &'static str {
+    match *track_type {
+        TrackType::Audio | TrackType::Cdg => "AUDIO",
+        TrackType::Mode(1, 2352) => "MODE1_RAW",
+        TrackType::Mode(1, _) => "MODE1",
+        TrackType::Mode(_, 2048) => "MODE2_FORM1",
+        TrackType::Mode(_, 2324) => "MODE2_FORM2",
+        TrackType::Mode(_, 2352) | TrackType::Cdi(2352) => "MODE2_RAW",
+        TrackType::Mode(..) | TrackType::Cdi(_) => "MODE2",
+    }
+}
+
+/// A track of a `.toc` file while it is being parsed.
+struct TocTrack {
+    track: Track,
+
+    /// Name of the file containing the data of the track.
+    file: String,
+
+    /// End of the data of the track in the file in frames, if its length was given.
+    end: Option<i64>,
+
+    /// Length of the silence before the track in frames.
+    pregap: i64,
+}
+
+/// Returns the track type of the cdrdao track mode `mode`.
+fn toc_track_type(mode: &str) -> Result<TrackType, Error> {
+    match mode.to_uppercase().as_str() {
+        "AUDIO" => Ok(TrackType::Audio),
+        "MODE1" => Ok(TrackType::Mode(1, 2048)),
+        "MODE1_RAW" => Ok(TrackType::Mode(1, 2352)),
+        "MODE2" | "MODE2_FORM_MIX" => Ok(TrackType::Mode(2, 2336)),
+        "MODE2_FORM1" => Ok(TrackType::Mode(2, 2048)),
+        "MODE2_FORM2" => Ok(TrackType::Mode(2, 2324)),
+        "MODE2_RAW" => Ok(TrackType::Mode(2, 2352)),
+        _ => Err(format!("Invalid toc track mode: {:?}", mode).into()),
+    }
+}
+
+/// Parses a time of a `.toc` file, either as `MM:SS:FF` or as a number of samples.
+fn parse_toc_time(value: &str) -> Result<Time, Error> {
+    let parts = value
+        .split(':')
+        .map(|part| part.parse::<i64>())
+        .collect::<Result<Vec<_>, _>>()?;
+    match parts.as_slice() {
+        // 588 samples make up a frame.
+        [samples] => Ok(Time::from_frames(samples / 588)),
+        [mins, secs, frames] if *secs < 60 && *frames < FPS => {
+            Ok(Time::from_frames((mins * 60 + secs) * FPS + frames))
+        }
+        [_, _, _] => Err(Error::MalformedTime(format!(
+            "Invalid toc time {:?}, seconds have to be less than 60 and frames less than {}.",
+            value, FPS
+        ))),
+        _ => Err(format!("Invalid toc time: {:?}", value).into()),
+    }
+}
+
+/// Quotes a string of a `.toc` file, escaping backslashes and quotes C-style.
+fn toc_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Splits a line of a `.toc` file into words, keeping quoted strings together and dropping
+/// `//` comments.
+fn toc_words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        } else if c == '"' {
+            let mut word = String::new();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => word.extend(chars.next()),
+                    c => word.push(c),
+                }
+            }
+            words.push(word);
+        } else if c == '/' && chars.peek() == Some(&'/') {
+            break;
+        } else {
+            let mut word = c.to_string();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            words.push(word);
+        }
+    }
+    words
+}
+
+/// Escapes the characters with a special meaning in ffmpeg metadata files with a backslash.
+fn escape_ffmetadata(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Progress events emitted by `Tracklist::parse_with_callback`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseEvent {
+    /// Parsing of the file with the given name started.
+    FileStarted(String),
+
+    /// The track with the given number was parsed.
+    TrackParsed(u32),
+}
+
+/// One file described by a tracklist.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TrackFile {
+    /// List of tracks contained in the file.
+    pub tracks: Vec<Track>,
+
+    /// The filename, empty if the cue sheet had no FILE line.
+    pub name: String,
+
+    /// The format of the file.
+    pub format: FileFormat,
+}
+
+impl TrackFile {
+    /// Sets the duration of the last track from the total length of the audio file.
+    ///
+    /// With just a cue sheet the duration of the last track is unknown, a decoder however knows
+    /// the length of the file. Returns an error if `total` is earlier than an index of the file or
+    /// if the last track has no `INDEX 01`.
+    pub fn set_total_length(&mut self, total: Time) -> Result<(), Error> {
+        let last_index = self
+            .tracks
+            .iter()
+            .flat_map(|track| track.index.iter())
+            .map(|index| &index.1)
+            .max();
+        if let Some(last_index) = last_index {
+            if total < *last_index {
+                return Err(format!(
+                    "Total length {} is before the index at {}.",
+                    total, last_index
+                )
+                .into());
+            }
+        }
+
+        let track = self.tracks.last_mut().ok_or("The file has no tracks.")?;
+        let start = track
+            .index_time(1)
+            .cloned()
+            .ok_or("The last track has no INDEX 01.")?;
+        track.duration = total.checked_sub(&start);
+        Ok(())
+    }
+
+    /// Clamps every index time exceeding `length` down to `length`.
+    ///
+    /// This repairs cue sheets whose indices overrun the end of the audio file. Durations reaching
+    /// past `length` are shortened to end there, so a track starting at `length` ends up with a
+    /// duration of zero. Returns the `(track number, index number)` of every index which was
+    /// clamped.
+    pub fn clamp_to_length(&mut self, length: Time) -> Vec<(u32, u32)> {
+        let mut clamped = Vec::new();
+        for track in &mut self.tracks {
+            for index in &mut track.index {
+                if index.1 > length {
+                    index.1 = length.clone();
+                    clamped.push((track.number, index.0));
+                }
+            }
+
+            let start = match track.index_time(1) {
+                Some(start) => start.clone(),
+                None => continue,
+            };
+            let overruns = match track.duration {
+                Some(ref duration) => start.clone() + duration.clone() > length,
+                None => false,
+            };
+            if overruns {
+                track.duration = length.checked_sub(&start);
+            }
+        }
+        clamped
+    }
+
+    /// Returns the spans of the file up to `total` which are not covered by any track.
+    ///
+    /// A track covers the time from its `INDEX 01` to its end, which is given by its duration, or
+    /// if that isn't known by the first index of the next track or `total`. So pregaps before
+    /// `INDEX 01` count as uncovered, as does the time after a track whose duration ends before
+    /// `total`.
+    pub fn uncovered_regions(&self, total: Time) -> Vec<(Time, Time)> {
+        let total = total.total_frames();
+        let mut covered = Vec::new();
+        for (i, track) in self.tracks.iter().enumerate() {
+            let start = match track.index_time(1) {
+                Some(start) => start.total_frames(),
+                None => continue,
+            };
+            let end = match track.duration {
+                Some(ref duration) => start + duration.total_frames(),
+                None => self.tracks[i + 1..]
+                    .iter()
+                    .filter_map(|next| next.index.first())
+                    .map(|index| index.1.total_frames())
+                    .next()
+                    .unwrap_or(total),
+            };
+            covered.push((start, end.min(total)));
+        }
+        covered.sort();
+
+        let mut regions = Vec::new();
+        let mut position = 0;
+        for (start, end) in covered {
+            if start > position {
+                regions.push((Time::from_frames(position), Time::from_frames(start)));
+            }
+            position = position.max(end);
+        }
+        if position < total {
+            regions.push((Time::from_frames(position), Time::from_frames(total)));
+        }
+        regions
+    }
+
+    /// Returns the boundaries for splitting the file into one file per track, as
+    /// `(number, start, end)`.
+    ///
+    /// Tracks start at their `INDEX 01` and end at the `INDEX 01` of the next track, so pregaps
+    /// end up at the end of the previous track. The end of the last track is `None`, tracks
+    /// without `INDEX 01` are left out.
+    pub fn split_points(&self) -> Vec<(u32, Time, Option<Time>)> {
+        let starts: Vec<(u32, Time)> = self
+            .tracks
+            .iter()
+            .filter_map(|track| Some((track.number, track.start()?)))
+            .collect();
+        starts
+            .iter()
+            .enumerate()
+            .map(|(i, &(number, ref start))| {
+                let end = starts.get(i + 1).map(|next| next.1.clone());
+                (number, start.clone(), end)
+            })
+            .collect()
+    }
+
+    /// Returns the length of the hidden track before the first track (HTOA), the time between
+    /// the `INDEX 00` and `INDEX 01` of the first track.
+    ///
+    /// Returns `None` if the first track has no `INDEX 00` or no `INDEX 01` after it.
+    pub fn hidden_track_length(&self) -> Option<Time> {
+        let track = self.tracks.first()?;
+        track.index_time(1)?.checked_sub(track.index_time(0)?)
+    }
+
+    /// Consumes a `FILE` command and the tracks following it.
+    ///
+    /// `lines` are the line numbers of all commands of the cue sheet, ending with those of
+    /// `commands`.
+    fn consume<F: FnMut(ParseEvent)>(
+        commands: &mut Vec<Command>,
+        lines: &[usize],
+        warnings: &mut Vec<String>,
+        callback: &mut F,
+    ) -> Result<Self, Error> {
+        if let Some(Command::File(name, format)) = commands.first().cloned() {
+            commands.remove(0);
+            callback(ParseEvent::FileStarted(name.clone()));
+            let mut tracks: Vec<Track> = Vec::new();
+            let mut last_time: Option<Time> = None;
+
+            while let Some(&Command::Track(..)) = commands.first() {
+                let track = Track::consume(commands, lines, warnings)?;
+                if track.index.len() > 0 {
+                    if let Some(start) = last_time {
+                        // Index times going backwards leave the duration unknown.
+                        let stop = &track.index[0].1;
+                        let duration = stop.checked_sub(&start);
+
+                        let track_n = tracks.len();
+                        if let Some(last_track) = tracks.get_mut(track_n - 1) {
+                            (*last_track).duration = duration;
+                        }
+                    }
+
+                    // INDEX 01 is the start of the track, without it the duration is unknown.
+                    last_time = track.index_time(1).cloned();
+                } else {
+                    last_time = None;
+                }
+
+                callback(ParseEvent::TrackParsed(track.number));
+                tracks.push(track);
+            }
+            Ok(TrackFile {
+                tracks,
+                name,
+                format,
+            })
+        } else {
+            Err("TrackFile::consume called but no Track command found.".into())
+        }
+    }
+}
+
+impl fmt::Display for TrackFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} [{}, {} tracks]",
+            self.name,
+            self.format,
+            self.tracks.len()
+        )
+    }
+}
+
+/// One track described by a tracklist.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Track {
+    /// Title of the track.
+    pub title: Option<String>,
+
+    /// Type of the track.
+    pub track_type: TrackType,
+
+    /// Duration of the track, if it was possible to determine it.
+    ///
+    /// This is only possible if tracks have index commands attached to them.
+    /// Also note that with just a cue file it is usually not possible to determine the duration of
+    /// the last track in the list.
+    pub duration: Option<Time>,
+
+    /// Index commands attached to this track (if any).
+    pub index: Vec<Index>,
+
+    /// Track number as provided in the cue sheet.
+    pub number: u32,
+
+    /// The performer of the track if any was stated.
+    pub performer: Option<String>,
+
+    /// The songwriter of the track if any was stated.
+    pub songwriter: Option<String>,
+
+    /// International Standard Recording Code of this track.
+    ///
+    /// ISRCs are case-insensitive, so the code is normalized to uppercase when parsing.
+    pub isrc: Option<String>,
+
+    /// Date of the track from a `REM DATE` inside of the track.
+    ///
+    /// Compilations use this for the original release year of every track.
+    pub date: Option<String>,
+
+    /// Subcode flags of the track as given by `FLAGS` commands, in order.
+    pub flags: Vec<TrackFlag>,
+
+    /// Length of the silence after the track as given by a `POSTGAP` command.
+    ///
+    /// Like `PREGAP`, this silence is not part of the audio file.
+    pub postgap: Option<Time>,
+}
+
+type Index = (u32, Time);
+
+impl Track {
+    /// Returns the time of the index with the given number, if present.
+    pub(crate) fn index_time(&self, number: u32) -> Option<&Time> {
+        self.index.iter().find(|i| i.0 == number).map(|i| &i.1)
+    }
+
+    /// Returns the time of `INDEX 01`, where playback of the track starts.
+    pub fn start(&self) -> Option<Time> {
+        self.index_time(1).cloned()
+    }
+
+    /// Returns the time at which the track ends, its `start` plus its duration.
+    ///
+    /// Together with `start` this gives the half-open interval `[start, end)` of the track.
+    pub fn end(&self) -> Option<Time> {
+        Some(self.start()? + self.duration.clone()?)
+    }
+
+    /// Returns the time of the highest-numbered index of the track.
+    ///
+    /// Together with the `INDEX 01` time this delimits the annotated extent of the track.
+    pub fn last_index_time(&self) -> Option<Time> {
+        self.index.iter().max_by_key(|i| i.0).map(|i| i.1.clone())
+    }
+
+    /// Returns the metadata of the track as tags named like the common Vorbis comment fields.
+    ///
+    /// The tags are `TRACKNUMBER` and, where known, `TITLE`, `ARTIST` (the performer), `DATE` and
+    /// `ISRC`.
+    pub fn as_tags(&self) -> BTreeMap<String, String> {
+        let mut tags = BTreeMap::new();
+        tags.insert("TRACKNUMBER".to_string(), self.number.to_string());
+        let fields = [
+            ("TITLE", &self.title),
+            ("ARTIST", &self.performer),
+            ("DATE", &self.date),
+            ("ISRC", &self.isrc),
+        ];
+        for &(key, value) in fields.iter() {
+            if let Some(ref value) = *value {
+                tags.insert(key.to_string(), value.clone());
+            }
+        }
+        tags
+    }
+
+    /// Returns the start (`INDEX 01`) of the track as an index of an audio sample frame at the
+    /// given `sample_rate`.
+    ///
+    /// The value is computed as `total_frames * sample_rate / 75` and rounded down. For the common
+    /// rates of 44100 and 48000 Hz every CD frame spans a whole number of samples, so no rounding
+    /// takes place. Returns `None` if the track has no `INDEX 01` or it is negative.
+    pub fn start_sample(&self, sample_rate: u32) -> Option<u64> {
+        let start = u64::try_from(self.index_time(1)?.total_frames()).ok()?;
+        Some(start * u64::from(sample_rate) / FPS as u64)
+    }
+
+    /// Consumes a `TRACK` command and the commands belonging to the track.
+    ///
+    /// `lines` are the line numbers of all commands of the cue sheet, ending with those of
+    /// `commands`.
+    fn consume(
+        commands: &mut Vec<Command>,
+        lines: &[usize],
+        warnings: &mut Vec<String>,
+    ) -> Result<Track, Error> {
+        if let Some(Command::Track(number, track_type)) = commands.first().cloned() {
+            commands.remove(0);
+            let mut title = None;
+            let mut performer = None;
+            let mut songwriter = None;
+            let mut isrc = None;
+            let mut date = None;
+            let mut flags = Vec::new();
+            let mut postgap = None;
+            let mut index = Vec::new();
+
+            while commands.len() > 0 {
+                let line = lines[lines.len() - commands.len()];
+                match commands[0].clone() {
+                    Command::Performer(p) => {
+                        performer = Some(p);
+                        commands.remove(0);
+                    }
+                    Command::Title(t) => {
+                        title = Some(t);
+                        commands.remove(0);
+                    }
+                    Command::Songwriter(s) => {
+                        songwriter = Some(s);
+                        commands.remove(0);
+                    }
+                    Command::Isrc(t) => {
+                        isrc = Some(t.to_uppercase());
+                        commands.remove(0);
+                    }
+                    Command::Catalog(_) => {
+                        warnings.push(format!(
+                            "line {}: Ignoring CATALOG inside of track {}.",
+                            line, number
+                        ));
+                        commands.remove(0);
+                    }
+                    Command::Rem(key, value) => {
+                        if key.eq_ignore_ascii_case("DATE") {
+                            date = Some(value);
+                        } else {
+                            warnings.push(format!(
+                                "line {}: Ignoring REM {} inside of track {}.",
+                                line,
+                                key.to_uppercase(),
+                                number
+                            ));
+                        }
+                        commands.remove(0);
+                    }
+                    Command::Pregap(time) => {
+                        let next_command = commands
+                            .get(1)
+                            .ok_or_else(|| {
+                                Error::from("Pregap is the last command in the track!")
+                                    .at_line(line)
+                            })?
+                            .to_owned();
+
+                        let first_index;
+                        match next_command {
+                            Command::Index(_, time) => first_index = time,
+                            _ => {
+                                return Err(Error::from("Pregap is not followed by an index!")
+                                    .at_line(line));
+                            }
+                        }
+                        let diff = first_index.total_frames() - time.total_frames();
+                        if diff < 0 {
+                            // Typically a PREGAP on track 1, whose INDEX 01 is at 00:00:00.
+                            warnings.push(format!(
+                                "line {}: Clamping PREGAP {} of track {} to the start of the file.",
+                                line, time, number
+                            ));
+                        }
+                        index.push((0, Time::from_frames(diff.max(0))));
+                        commands.remove(0);
+                    }
+                    Command::Index(i, time) => {
+                        index.push((i, time));
+                        commands.remove(0);
+                    }
+                    Command::Postgap(time) => {
+                        postgap = Some(time);
+                        commands.remove(0);
+                    }
+                    Command::Flags(f) => {
+                        flags.extend(f);
+                        commands.remove(0);
+                    }
+                    _ => break,
+                }
+            }
+
+            Ok(Track {
+                title,
+                track_type,
+                duration: None,
+                index,
+                number,
+                performer,
+                songwriter,
+                isrc,
+                date,
+                flags,
+                postgap,
+            })
+        } else {
+            Err("Track::consume called but no Track command found.".into())
+        }
+    }
+}
+
+impl fmt::Display for Track {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02}.", self.number)?;
+        if let Some(ref title) = self.title {
+            write!(f, " {}", title)?;
+        }
+        if let Some(start) = self.index_time(1) {
+            write!(f, " [{}]", start)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"REM GENRE "Progressive Rock"
+REM DATE 1985
+REM DISCID DC0E6811
+REM COMMENT "ExactAudioCopy v0.95b3"
+REM DISCNUMBER 2
+REM TOTALDISCS 2
+CATALOG 0724349703629
 PERFORMER "Marillion"
 TITLE "Misplaced Childhood (CD2: Demo)"
 FILE "Marillion - Misplaced Childhood (CD2).flac" WAVE
   TRACK 01 AUDIO
-    TITLE "Lady Nina"
-    PERFORMER "Marillion"
-    ISRC GBAYE9801904
+    TITLE "Lady Nina"
+    PERFORMER "Marillion"
+    ISRC GBAYE9801904
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Freaks"
+    PERFORMER "Marillion"
+    ISRC GBAYE9801905
+    INDEX 00 05:47:50
+    INDEX 01 05:50:10
+  TRACK 03 AUDIO
+    TITLE "Kayleigh (Alternate Mix)"
+    PERFORMER "Marillion"
+    ISRC GBAYE9801906
+    INDEX 00 09:55:60
+    INDEX 01 09:58:20
+  TRACK 04 AUDIO
+    TITLE "Lavender Blue"
+    PERFORMER "Marillion"
+    ISRC GBAYE9801907
+    INDEX 00 13:57:60
+    INDEX 01 14:01:72
+  TRACK 05 AUDIO
+    TITLE "Heart of Lothian (Extended Mix)"
+    PERFORMER "Marillion"
+    ISRC GBAYE9801908
+    INDEX 00 18:23:15
+    INDEX 01 18:24:12
+  TRACK 06 AUDIO
+    TITLE "Pseudo Silk Kimono (Album Demo)"
+    PERFORMER "Marillion"
+    ISRC GBAYE9801909
+    INDEX 00 24:10:15
+    INDEX 01 24:18:17
+  TRACK 07 AUDIO
+    TITLE "Kayleigh (Album Demo)"
+    PERFORMER "Marillion"
+    ISRC GBAYE9801910
+    INDEX 01 26:29:70
+  TRACK 08 AUDIO
+    TITLE "Lavender (Album Demo)"
+    PERFORMER "Marillion"
+    ISRC GBAYE9801911
+    INDEX 01 30:36:20
+  TRACK 09 AUDIO
+    TITLE "Bitter Suite (I. Brief Encounter II. Lost Weekend) (Album Demo)"
+    PERFORMER "Marillion"
+    ISRC GBAYE9801912
+    INDEX 01 33:14:10
+    INDEX 02 34:52:55
+  TRACK 10 AUDIO
+    TITLE "Lords of the Backstage (Album Demo)"
+    PERFORMER "Marillion"
+    ISRC GBAYE9801913
+    INDEX 01 36:08:70
+  TRACK 11 AUDIO
+    TITLE "Blue Angel (Album Demo)"
+    PERFORMER "Marillion"
+    ISRC GBAYE9801914
+    INDEX 01 37:55:50
+  TRACK 12 AUDIO
+    TITLE "Misplaced Rendezvous (Album Demo)"
+    PERFORMER "Marillion"
+    ISRC GBAYE9801915
+    INDEX 01 39:42:17
+    INDEX 02 41:01:57
+  TRACK 13 AUDIO
+    TITLE "Heart of Lothian (I. Wide Boy II. Curtain Call) (Album Demo)"
+    PERFORMER "Marillion"
+    ISRC GBAYE9801916
+    INDEX 01 41:38:57
+    INDEX 02 44:26:35
+  TRACK 14 AUDIO
+    TITLE "Waterhole (Expresso Bongo) (Album Demo)"
+    PERFORMER "Marillion"
+    ISRC GBAYE9801917
+    INDEX 00 45:27:70
+    INDEX 01 45:28:15
+  TRACK 15 AUDIO
+    TITLE "Passing Strangers (I. Mylo II. Perimeter Walk III. Threshold) (Album Demo)"
+    PERFORMER "Marillion"
+    ISRC GBAYE9801918
+    INDEX 01 47:28:62
+    INDEX 02 49:40:52
+    INDEX 03 51:28:62
+    INDEX 04 53:45:72
+  TRACK 16 AUDIO
+    TITLE "Childhoods End? (Album Demo)"
+    PERFORMER "Marillion"
+    ISRC GBAYE9801919
+    INDEX 01 56:45:67
+  TRACK 17 AUDIO
+    TITLE "White Feather (Album Demo)"
+    PERFORMER "Marillion"
+    ISRC GBAYE9801920
+    INDEX 01 59:09:50"#;
+
+    #[test]
+    fn sample() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        assert_eq!(tracklist.genre.unwrap(), "Progressive Rock".to_string());
+        assert_eq!(tracklist.date.unwrap(), "1985".to_string());
+        assert_eq!(tracklist.discid.unwrap(), "DC0E6811".to_string());
+        assert_eq!(tracklist.comment.unwrap(), "ExactAudioCopy v0.95b3".to_string());
+        assert_eq!(tracklist.discnumber.unwrap(), 2);
+        assert_eq!(tracklist.totaldiscs.unwrap(), 2);
+        assert_eq!(tracklist.catalog.unwrap(), "0724349703629".to_string());
+        assert_eq!(tracklist.performer.unwrap(), "Marillion".to_string());
+        assert_eq!(tracklist.title.unwrap(), "Misplaced Childhood (CD2: Demo)".to_string());
+
+        let files = tracklist.files;
+        assert_eq!(files.len(), 1);
+
+        let ref f = files[0];
+        assert_eq!(f.name, "Marillion - Misplaced Childhood (CD2).flac".to_string());
+        assert_eq!(f.format, FileFormat::Wave);
+
+        let ref tracks = f.tracks;
+        assert_eq!(tracks.len(), 17);
+
+        assert_eq!(tracks[0].number, 1);
+        assert_eq!(tracks[0].track_type, TrackType::Audio);
+        assert_eq!(tracks[0].title, Some("Lady Nina".to_string()));
+        assert_eq!(tracks[0].performer, Some("Marillion".to_string()));
+        assert_eq!(tracks[0].isrc, Some("GBAYE9801904".to_string()));
+        assert_eq!(tracks[0].index[0], (1, Time::new(0, 0, 0)));
+        assert_eq!(tracks[0].duration, Some(Time::new(5, 47, 50)));
+
+        assert_eq!(tracks[1].number, 2);
+        assert_eq!(tracks[1].track_type, TrackType::Audio);
+        assert_eq!(tracks[1].title, Some("Freaks".to_string()));
+        assert_eq!(tracks[1].performer, Some("Marillion".to_string()));
+        assert_eq!(tracks[1].isrc, Some("GBAYE9801905".to_string()));
+        assert_eq!(tracks[1].index[0], (0, Time::new(5, 47, 50)));
+        assert_eq!(tracks[1].index[1], (1, Time::new(5, 50, 10)));
+        assert_eq!(tracks[1].duration, Some(Time::new(4, 5, 50)));
+
+        assert_eq!(tracks[14].number, 15);
+        assert_eq!(tracks[14].track_type, TrackType::Audio);
+        assert_eq!(tracks[14].title, Some("Passing Strangers (I. Mylo II. Perimeter Walk III. Threshold) (Album Demo)".to_string()));
+        assert_eq!(tracks[14].performer, Some("Marillion".to_string()));
+        assert_eq!(tracks[14].isrc, Some("GBAYE9801918".to_string()));
+        assert_eq!(tracks[14].index[0], (1, Time::new(47, 28, 62)));
+        assert_eq!(tracks[14].index[1], (2, Time::new(49, 40, 52)));
+        assert_eq!(tracks[14].index[2], (3, Time::new(51, 28, 62)));
+        assert_eq!(tracks[14].index[3], (4, Time::new(53, 45, 72)));
+        assert_eq!(tracks[14].duration, Some(Time::new(9, 17, 5)));
+
+        assert_eq!(tracks[15].number, 16);
+        assert_eq!(tracks[15].track_type, TrackType::Audio);
+        assert_eq!(tracks[15].title, Some("Childhoods End? (Album Demo)".to_string()));
+        assert_eq!(tracks[15].performer, Some("Marillion".to_string()));
+        assert_eq!(tracks[15].isrc, Some("GBAYE9801919".to_string()));
+        assert_eq!(tracks[15].index[0], (1, Time::new(56, 45, 67)));
+        assert_eq!(tracks[15].duration, Some(Time::new(2, 23, 58)));
+    }
+
+    #[test]
+    fn pregap() {
+        let src = r#"FILE "disc.img" BINARY
+                       TRACK 01 MODE1/2352
+                         INDEX 01 00:00:00
+                       TRACK 02 AUDIO
+                         PREGAP 00:02:00
+                         INDEX 01 58:41:36
+                       TRACK 03 AUDIO
+                         INDEX 00 61:06:08
+                         INDEX 01 61:08:08"#;
+
+        let tracklist = Tracklist::parse(src).unwrap();
+
+        let ref f = tracklist.files[0];
+        let ref tracks = f.tracks;
+
+        assert_eq!(tracks[0].index[0], (1, Time::new(0, 0, 0)));
+        assert_eq!(tracks[1].index[0], (0, Time::new(58, 39, 36)));
+        assert_eq!(tracks[1].index[1], (1, Time::new(58, 41, 36)));
+        assert_eq!(tracks[2].index[0], (0, Time::new(61, 06, 08)));
+        assert_eq!(tracks[2].index[1], (1, Time::new(61, 08, 08)));
+    }
+
+    #[test]
+    fn windows_path() {
+        let source = r#"FILE "C:\Music\12:34:56 Live\disc.flac" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00"#;
+
+        let tracklist = Tracklist::parse(source).unwrap();
+        let name = r"C:\Music\12:34:56 Live\disc.flac";
+        assert_eq!(tracklist.files[0].name, name.to_string());
+
+        let reparsed = Tracklist::parse(&tracklist.to_cue_string()).unwrap();
+        assert_eq!(reparsed.files[0].name, name.to_string());
+    }
+
+    #[test]
+    fn index_before_metadata() {
+        let src = r#"FILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+    TITLE "First"
+    PERFORMER "Someone"
+  TRACK 02 AUDIO
+    INDEX 00 03:10:00
+    INDEX 01 03:12:00
+    PERFORMER "Someone Else"
+    TITLE "Second""#;
+
+        let tracklist = Tracklist::parse(src).unwrap();
+        let tracks = &tracklist.files[0].tracks;
+
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].title, Some("First".to_string()));
+        assert_eq!(tracks[0].performer, Some("Someone".to_string()));
+        assert_eq!(tracks[0].index[0], (1, Time::new(0, 0, 0)));
+        assert_eq!(tracks[0].duration, Some(Time::new(3, 10, 0)));
+        assert_eq!(tracks[1].title, Some("Second".to_string()));
+        assert_eq!(tracks[1].performer, Some("Someone Else".to_string()));
+        assert_eq!(tracks[1].index[1], (1, Time::new(3, 12, 0)));
+    }
+
+    #[test]
+    fn fingerprint() {
+        let retagged = SAMPLE
+            .replace("REM GENRE \"Progressive Rock\"", "REM GENRE Rock")
+            .replace("PERFORMER \"Marillion\"", "PERFORMER \"Fish\"")
+            .replace("TITLE \"Lady Nina\"", "TITLE \"Lady Nina (Remaster)\"");
+        let original = Tracklist::parse(SAMPLE).unwrap();
+        let retagged = Tracklist::parse(&retagged).unwrap();
+        assert_eq!(original.fingerprint(), retagged.fingerprint());
+
+        let shifted = SAMPLE.replace("INDEX 01 26:29:70", "INDEX 01 26:29:71");
+        let shifted = Tracklist::parse(&shifted).unwrap();
+        assert_ne!(original.fingerprint(), shifted.fingerprint());
+
+        // The value must not change between releases, caches rely on it.
+        assert_eq!(original.fingerprint(), 16_268_289_899_603_671_689);
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn year() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        assert_eq!(tracklist.year(), Some(1985));
+
+        let source = SAMPLE.replace("REM DATE 1985", "REM DATE \"Recorded 1985\"");
+        let tracklist = Tracklist::parse(&source).unwrap();
+        assert_eq!(tracklist.year(), Some(1985));
+
+        let source = SAMPLE.replace("REM DATE 1985", "REM DATE \"Take 0042\"");
+        let tracklist = Tracklist::parse(&source).unwrap();
+        assert_eq!(tracklist.year(), None);
+    }
+
+    #[test]
+    fn display() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let file = &tracklist.files[0];
+
+        assert_eq!(
+            file.to_string(),
+            "Marillion - Misplaced Childhood (CD2).flac [WAVE, 17 tracks]"
+        );
+        assert_eq!(
+            file.tracks[6].to_string(),
+            "07. Kayleigh (Album Demo) [26:29:70]"
+        );
+    }
+
+    #[test]
+    fn bare_tracks_round_trip() {
+        let source = r#"FILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    TITLE "Intro"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Outro"
+    INDEX 01 02:10:20
+"#;
+
+        let tracklist = Tracklist::parse(source).unwrap();
+        let tracks = &tracklist.files[0].tracks;
+        assert_eq!(tracks[0].performer, None);
+        assert_eq!(tracks[0].isrc, None);
+        assert_eq!(tracks[1].performer, None);
+        assert_eq!(tracks[1].isrc, None);
+
+        let emitted = tracklist.to_cue_string();
+        assert_eq!(emitted, source);
+        assert_eq!(Tracklist::parse(&emitted).unwrap().files, tracklist.files);
+    }
+
+    #[test]
+    fn file_index_of_track() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        assert_eq!(tracklist.file_index_of_track(1), Some(0));
+        assert_eq!(tracklist.file_index_of_track(17), Some(0));
+        assert_eq!(tracklist.file_index_of_track(18), None);
+
+        let source = r#"FILE "one.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 01 03:00:00
+FILE "two.wav" WAVE
+  TRACK 03 AUDIO
+    INDEX 01 00:00:00"#;
+        let tracklist = Tracklist::parse(source).unwrap();
+        assert_eq!(tracklist.file_index_of_track(2), Some(0));
+        assert_eq!(tracklist.file_index_of_track(3), Some(1));
+    }
+
+    #[test]
+    fn max_tracks() {
+        let options = ParseOptions {
+            max_tracks: Some(17),
+            ..Default::default()
+        };
+        assert!(Tracklist::parse_with_options(SAMPLE, &options).is_ok());
+
+        let options = ParseOptions {
+            max_tracks: Some(16),
+            ..Default::default()
+        };
+        match Tracklist::parse_with_options(SAMPLE, &options) {
+            Err(Error::Parse { line, .. }) => assert_eq!(line, 103),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn index_point_count() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        assert_eq!(tracklist.index_point_count(), 29);
+    }
+
+    #[test]
+    fn trailing_comment() {
+        let source = SAMPLE.replace(
+            "PERFORMER \"Marillion\"\nTITLE",
+            "PERFORMER \"Marillion\" ; band\nTITLE",
+        );
+        let tracklist = Tracklist::parse(&source).unwrap();
+        assert_eq!(tracklist.performer, Some("Marillion".to_string()));
+        assert_eq!(
+            tracklist.title,
+            Some("Misplaced Childhood (CD2: Demo)".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_bytes() {
+        let source = b"TITLE \"Caf\xe9\"\nFILE \"disc.wav\" WAVE";
+        let tracklist = Tracklist::parse_bytes(source).unwrap();
+        assert_eq!(tracklist.title, Some("Caf\u{e9}".to_string()));
+        assert_eq!(tracklist.encoding, None);
+
+        let source = b"TITLE \"Caf\xc3\xa9\"\nFILE \"disc.wav\" WAVE";
+        let tracklist = Tracklist::parse_bytes(source).unwrap();
+        assert_eq!(tracklist.title, Some("Caf\u{e9}".to_string()));
+
+        // Valid UTF-8, but the hint says otherwise.
+        let source = b"REM ENCODING ISO-8859-1\nTITLE \"Caf\xc3\xa9\"\nFILE \"disc.wav\" WAVE";
+        let tracklist = Tracklist::parse_bytes(source).unwrap();
+        assert_eq!(tracklist.title, Some("Caf\u{c3}\u{a9}".to_string()));
+        assert_eq!(tracklist.encoding, Some("ISO-8859-1".to_string()));
+    }
+
+    #[test]
+    fn track_time_shares() {
+        let mut tracklist = Tracklist::parse(SAMPLE).unwrap();
+        assert!(tracklist.track_time_shares().is_empty());
+
+        tracklist.files[0].tracks[16].duration = Some(Time::new(4, 0, 0));
+        let shares = tracklist.track_time_shares();
+        assert_eq!(shares.len(), 17);
+        assert_eq!(shares[0].0, 1);
+
+        let sum: f64 = shares.iter().map(|s| s.1).sum();
+        assert!((sum - 1.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn trailing_track_without_index() {
+        let source = r#"FILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    TITLE "First"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Second""#;
+
+        let tracklist = Tracklist::parse(source).unwrap();
+        let tracks = &tracklist.files[0].tracks;
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[1].title, Some("Second".to_string()));
+        assert!(tracks[1].index.is_empty());
+        assert_eq!(tracks[1].duration, None);
+
+        assert!(TrackFile::consume(&mut Vec::new(), &[], &mut Vec::new(), &mut |_| {}).is_err());
+        assert!(Track::consume(&mut Vec::new(), &[], &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn same_disc() {
+        let original = Tracklist::parse(SAMPLE).unwrap();
+        let retagged = SAMPLE
+            .replace("TITLE \"Freaks\"", "TITLE \"Freaks (Demo)\"")
+            .replace("ExactAudioCopy v0.95b3", "XLD")
+            .replace("INDEX 01 05:50:10", "INDEX 01 05:50:12");
+        let retagged = Tracklist::parse(&retagged).unwrap();
+        assert!(original.same_disc(&retagged));
+
+        let other = SAMPLE.replace("INDEX 01 05:50:10", "INDEX 01 05:52:10");
+        let other = Tracklist::parse(&other).unwrap();
+        assert!(!original.same_disc(&other));
+
+        let other = SAMPLE.replace("REM DISCID DC0E6811", "REM DISCID DC0E6812");
+        let other = Tracklist::parse(&other).unwrap();
+        assert!(!original.same_disc(&other));
+    }
+
+    #[test]
+    fn frames_per_second() {
+        let source = r#"FILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 01 03:10:99"#;
+
+        assert!(Tracklist::parse(source).is_err());
+
+        let options = ParseOptions {
+            frames_per_second: 100,
+            ..Default::default()
+        };
+        let tracklist = Tracklist::parse_with_options(source, &options).unwrap();
+        assert_eq!(
+            tracklist.files[0].tracks[1].index[0],
+            (1, Time::new(3, 10, 99))
+        );
+    }
+
+    #[test]
+    fn clamp_to_length() {
+        let mut tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let file = &mut tracklist.files[0];
+        file.tracks[16].duration = Some(Time::new(4, 0, 0));
+
+        let clamped = file.clamp_to_length(Time::new(58, 0, 0));
+        assert_eq!(clamped, vec![(17, 1)]);
+        assert_eq!(file.tracks[16].index[0], (1, Time::new(58, 0, 0)));
+        assert_eq!(file.tracks[15].index[0], (1, Time::new(56, 45, 67)));
+        assert_eq!(file.tracks[15].duration, Some(Time::new(1, 14, 8)));
+        assert_eq!(file.tracks[16].duration, Some(Time::new(0, 0, 0)));
+        assert_eq!(file.tracks[14].duration, Some(Time::new(9, 17, 5)));
+
+        assert!(file.clamp_to_length(Time::new(58, 0, 0)).is_empty());
+    }
+
+    #[test]
+    fn lowercase_isrc() {
+        let source = SAMPLE.replace("ISRC GBAYE9801904", "ISRC gbaye9801904");
+        let tracklist = Tracklist::parse(&source).unwrap();
+        assert_eq!(
+            tracklist.files[0].tracks[0].isrc,
+            Some("GBAYE9801904".to_string())
+        );
+    }
+
+    #[test]
+    fn with_setters() {
+        let tracklist = Tracklist::parse(SAMPLE)
+            .unwrap()
+            .with_title("Misplaced Childhood")
+            .with_date("1985-06-17");
+        assert_eq!(tracklist.title, Some("Misplaced Childhood".to_string()));
+        assert_eq!(tracklist.date, Some("1985-06-17".to_string()));
+        assert_eq!(tracklist.performer, Some("Marillion".to_string()));
+
+        let tracklist = tracklist.with_performer("Fish");
+        assert_eq!(tracklist.performer, Some("Fish".to_string()));
+    }
+
+    #[test]
+    fn file_with_trailing_data() {
+        let source = r#"FILE "x.wav" WAVE // whatever
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00"#;
+
+        let tracklist = Tracklist::parse(source).unwrap();
+        assert_eq!(tracklist.files[0].name, "x.wav".to_string());
+        assert_eq!(tracklist.files[0].format, FileFormat::Wave);
+        assert_eq!(tracklist.files[0].tracks.len(), 1);
+        assert_eq!(tracklist.warnings.len(), 1);
+        assert!(tracklist.warnings[0].starts_with("line 1:"));
+
+        let options = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert!(Tracklist::parse_with_options(source, &options).is_err());
+    }
+
+    #[test]
+    fn titles() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let titles = tracklist.titles();
+        assert_eq!(titles.len(), 17);
+        assert_eq!(
+            &titles[..3],
+            &["Lady Nina", "Freaks", "Kayleigh (Alternate Mix)"]
+        );
+    }
+
+    #[test]
+    fn rating() {
+        let source = SAMPLE.replace("REM DATE 1985", "REM RATING 5\nREM Source CD");
+        let tracklist = Tracklist::parse(&source).unwrap();
+        assert_eq!(tracklist.rating, Some(5));
+        assert_eq!(tracklist.date, None);
+        assert_eq!(tracklist.rem.len(), 1);
+        assert_eq!(tracklist.rem["SOURCE"], "CD".to_string());
+
+        let reparsed = Tracklist::parse(&tracklist.to_cue_string()).unwrap();
+        assert_eq!(reparsed.rating, Some(5));
+        assert_eq!(reparsed.rem, tracklist.rem);
+    }
+
+    #[test]
+    fn invalid_frames() {
+        let source = r#"FILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:80
+  TRACK 02 AUDIO
+    INDEX 01 03:10:74"#;
+
+        let options = ParseOptions {
+            frames_per_second: 100,
+            ..Default::default()
+        };
+        let tracklist = Tracklist::parse_with_options(source, &options).unwrap();
+        assert_eq!(tracklist.invalid_frames(), vec![(1, 1)]);
+    }
+
+    #[test]
+    fn parse_with_callback() {
+        let mut events = Vec::new();
+        let tracklist = Tracklist::parse_with_callback(SAMPLE, |e| events.push(e)).unwrap();
+        assert_eq!(tracklist.files[0].tracks.len(), 17);
+
+        assert_eq!(
+            events[0],
+            ParseEvent::FileStarted("Marillion - Misplaced Childhood (CD2).flac".to_string())
+        );
+        let parsed: Vec<u32> = events
+            .iter()
+            .filter_map(|e| match *e {
+                ParseEvent::TrackParsed(number) => Some(number),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(parsed, (1..18).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn to_report() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let report = tracklist.to_report();
+        let lines: Vec<&str> = report.lines().collect();
+
+        assert_eq!(lines[0], "Album:     Misplaced Childhood (CD2: Demo)");
+        assert_eq!(lines[1], "Performer: Marillion");
+        assert_eq!(lines[2], "Tracks:    17");
+        assert_eq!(lines[4], "01. Lady Nina - Marillion (05:47)");
+        assert_eq!(
+            lines[20],
+            "17. White Feather (Album Demo) - Marillion (??:??)"
+        );
+    }
+
+    #[test]
+    fn misplaced_catalog_and_isrc() {
+        let source = r#"ISRC GBAYE9801904
+TITLE "Album"
+FILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    TITLE "First"
+    CATALOG 0724349703629
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Second"
+    INDEX 01 03:00:00"#;
+
+        let tracklist = Tracklist::parse(source).unwrap();
+        assert_eq!(tracklist.title, Some("Album".to_string()));
+        assert_eq!(tracklist.catalog, None);
+
+        let tracks = &tracklist.files[0].tracks;
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].index[0], (1, Time::new(0, 0, 0)));
+        assert_eq!(tracks[0].duration, Some(Time::new(3, 0, 0)));
+        assert_eq!(tracklist.warnings.len(), 2);
+    }
+
+    #[test]
+    fn average_pregap() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        // Gaps of tracks 2, 3, 4, 5, 6 and 14: (185 + 185 + 312 + 72 + 602 + 20) / 6 frames.
+        assert_eq!(tracklist.average_pregap(), Some(Time::from_frames(229)));
+
+        let source = r#"FILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00"#;
+        let tracklist = Tracklist::parse(source).unwrap();
+        assert_eq!(tracklist.average_pregap(), None);
+    }
+
+    #[test]
+    fn extract_file() {
+        let source = r#"TITLE "Live"
+REM LEADOUT 05:00:00
+FILE "one.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 00 00:00:00
+    INDEX 01 00:01:00
+  TRACK 02 AUDIO
+    INDEX 01 03:01:00
+FILE "two.wav" WAVE
+  TRACK 03 AUDIO
+    INDEX 01 00:02:00
+  TRACK 04 AUDIO
+    INDEX 01 02:00:00"#;
+        let tracklist = Tracklist::parse(source).unwrap();
+
+        let first = tracklist.extract_file(0).unwrap();
+        assert_eq!(first.leadout, None);
+        assert_eq!(first.title, Some("Live".to_string()));
+        assert_eq!(first.files.len(), 1);
+        assert_eq!(first.files[0].name, "one.wav".to_string());
+        let tracks = &first.files[0].tracks;
+        assert_eq!(tracks[0].index[0], (0, Time::new(0, 0, 0)));
+        assert_eq!(tracks[0].index[1], (1, Time::new(0, 0, 0)));
+        assert_eq!(tracks[1].index[0], (1, Time::new(3, 0, 0)));
+
+        let second = tracklist.extract_file(1).unwrap();
+        let numbers: Vec<u32> = second.files[0].tracks.iter().map(|t| t.number).collect();
+        assert_eq!(numbers, vec![1, 2]);
+        assert_eq!(second.leadout, Some(Time::new(4, 58, 0)));
+        let reparsed = Tracklist::parse(&second.to_cue_string()).unwrap();
+        assert_eq!(
+            reparsed.files[0].tracks[1].duration,
+            Some(Time::new(3, 0, 0))
+        );
+
+        assert!(tracklist.extract_file(2).is_none());
+    }
+
+    #[test]
+    fn smart_quotes() {
+        let source = "TITLE \u{201c}Misplaced Childhood\u{201d}\nFILE \"disc.wav\" WAVE";
+        let options = ParseOptions {
+            smart_quotes: true,
+            ..Default::default()
+        };
+        let tracklist = Tracklist::parse_with_options(source, &options).unwrap();
+        assert_eq!(tracklist.title, Some("Misplaced Childhood".to_string()));
+        assert!(tracklist.warnings.is_empty());
+
+        let options = ParseOptions {
+            smart_quotes: true,
+            strict: true,
+            ..Default::default()
+        };
+        assert!(Tracklist::parse_with_options(source, &options).is_err());
+    }
+
+    #[test]
+    fn tracks_by_duration_desc() {
+        let source = r#"FILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 01 02:00:00
+  TRACK 03 AUDIO
+    INDEX 01 07:00:00
+  TRACK 04 AUDIO
+    INDEX 01 07:30:00"#;
+        let mut tracklist = Tracklist::parse(source).unwrap();
+        let numbers: Vec<u32> = tracklist
+            .tracks_by_duration_desc()
+            .iter()
+            .map(|t| t.number)
+            .collect();
+        assert_eq!(numbers, vec![2, 1, 3, 4]);
+
+        tracklist.files[0].tracks[3].duration = Some(Time::new(3, 0, 0));
+        let numbers: Vec<u32> = tracklist
+            .tracks_by_duration_desc()
+            .iter()
+            .map(|t| t.number)
+            .collect();
+        assert_eq!(numbers, vec![2, 4, 1, 3]);
+    }
+
+    #[test]
+    fn rem_value_with_colons() {
+        let source = SAMPLE.replace(
+            "REM COMMENT \"ExactAudioCopy v0.95b3\"",
+            "REM COMMENT Ripped at 2023:01:01   12:00",
+        );
+        let tracklist = Tracklist::parse(&source).unwrap();
+        assert_eq!(
+            tracklist.comment,
+            Some("Ripped at 2023:01:01   12:00".to_string())
+        );
+        assert!(tracklist.warnings.is_empty());
+
+        let reparsed = Tracklist::parse(&tracklist.to_cue_string()).unwrap();
+        assert_eq!(reparsed.comment, tracklist.comment);
+    }
+
+    #[test]
+    fn validate_files() {
+        use std::{env, fs, process};
+
+        let dir = env::temp_dir().join(format!("cue_sheet_validate_{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("one.wav"), b"RIFF").unwrap();
+        fs::create_dir_all(dir.join("subdir")).unwrap();
+
+        let source = r#"FILE "one.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+FILE "two.wav" WAVE
+  TRACK 02 AUDIO
+    INDEX 01 00:00:00
+FILE "subdir" WAVE
+  TRACK 03 AUDIO
+    INDEX 01 00:00:00"#;
+        let tracklist = Tracklist::parse(source).unwrap();
+        let missing = tracklist.validate_files(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(missing, vec!["two.wav".to_string(), "subdir".to_string()]);
+    }
+
+    #[test]
+    fn multibyte_title() {
+        let title = "ミスプレイスト・チャイルドフッド 🎸 Café";
+        let source = SAMPLE.replace("Misplaced Childhood (CD2: Demo)", title);
+
+        let tracklist = Tracklist::parse(&source).unwrap();
+        assert_eq!(tracklist.title, Some(title.to_string()));
+
+        let reparsed = Tracklist::parse(&tracklist.to_cue_string()).unwrap();
+        assert_eq!(reparsed.title, Some(title.to_string()));
+    }
+
+    #[test]
+    fn cumulative_starts() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let starts = tracklist.cumulative_starts();
+        assert_eq!(starts.len(), 17);
+        assert_eq!(starts[0], Time::new(0, 0, 0));
+        assert_eq!(starts[1], Time::new(5, 50, 10));
+        assert_eq!(starts[16], Time::new(59, 9, 50));
+
+        let source = r#"FILE "one.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 01 03:00:00
+FILE "two.wav" WAVE
+  TRACK 03 AUDIO
+    INDEX 01 00:00:00"#;
+        let mut tracklist = Tracklist::parse(source).unwrap();
+        assert_eq!(tracklist.cumulative_starts().len(), 2);
+
+        tracklist.files[0].tracks[1].duration = Some(Time::new(2, 30, 0));
+        let starts = tracklist.cumulative_starts();
+        assert_eq!(starts[2], Time::new(5, 30, 0));
+    }
+
+    #[test]
+    fn no_trailing_newline() {
+        let lines = [
+            "FILE \"disc.wav\" WAVE",
+            "  TRACK 01 AUDIO",
+            "    INDEX 01 00:00:00",
+            "  TRACK 02 AUDIO",
+            "    INDEX 01 03:00:00",
+        ];
+        for newline in &["\n", "\r\n"] {
+            let tracklist = Tracklist::parse(&lines.join(newline)).unwrap();
+            let tracks = &tracklist.files[0].tracks;
+            assert_eq!(tracks.len(), 2);
+            assert_eq!(tracks[1].index, vec![(1, Time::new(3, 0, 0))]);
+        }
+    }
+
+    #[test]
+    fn duplicate_rems() {
+        let source = SAMPLE.replace(
+            "REM DATE 1985",
+            "REM DATE 1985\nREM GENRE Rock\nREM DATE 1985",
+        );
+        let tracklist = Tracklist::parse(&source).unwrap();
+        assert_eq!(tracklist.genre, Some("Progressive Rock".to_string()));
+        assert_eq!(tracklist.date, Some("1985".to_string()));
+        assert_eq!(tracklist.warnings.len(), 1);
+        assert!(tracklist.warnings[0].contains("GENRE"));
+    }
+
+    #[test]
+    fn last_index_time() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let tracks = &tracklist.files[0].tracks;
+        assert_eq!(tracks[14].last_index_time(), Some(Time::new(53, 45, 72)));
+        assert_eq!(tracks[0].last_index_time(), Some(Time::new(0, 0, 0)));
+    }
+
+    #[test]
+    fn to_chapters() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let chapters = tracklist.to_chapters();
+        assert_eq!(chapters.len(), 17);
+        assert_eq!(chapters[0], (Duration::new(0, 0), "Lady Nina".to_string()));
+        // 05:50:10 is 350 seconds and 10 frames.
+        assert_eq!(
+            chapters[1],
+            (Duration::new(350, 133_333_333), "Freaks".to_string())
+        );
+    }
+
+    #[test]
+    fn irregular_spacing() {
+        let irregular = SAMPLE.replace("INDEX 01 05:50:10", "INDEX   01 \t  05:50:10  ");
+
+        let regular = Tracklist::parse(SAMPLE).unwrap();
+        let irregular = Tracklist::parse(&irregular).unwrap();
+        assert_eq!(
+            irregular.files[0].tracks[1].index[1],
+            (1, Time::new(5, 50, 10))
+        );
+        assert_eq!(irregular.files, regular.files);
+    }
+
+    #[test]
+    fn has_hidden_track() {
+        let source = r#"FILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 00 00:00:00
+    INDEX 01 01:12:40
+  TRACK 02 AUDIO
+    INDEX 01 05:00:00"#;
+        assert!(Tracklist::parse(source).unwrap().has_hidden_track());
+        assert!(!Tracklist::parse(SAMPLE).unwrap().has_hidden_track());
+
+        let source = source.replace("INDEX 01 01:12:40", "INDEX 01 00:00:00");
+        assert!(!Tracklist::parse(&source).unwrap().has_hidden_track());
+    }
+
+    #[test]
+    fn embedded_cuesheet() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let embedded = tracklist.to_embedded_cuesheet();
+        let parsed = Tracklist::parse_embedded(&embedded).unwrap();
+        assert_eq!(parsed.files, tracklist.files);
+        assert_eq!(parsed.title, tracklist.title);
+
+        let tagged = format!("CUESHEET={}", embedded);
+        assert_eq!(
+            Tracklist::parse_embedded(&tagged).unwrap().files,
+            tracklist.files
+        );
+
+        let source = r#"FILE "one.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+FILE "two.wav" WAVE
+  TRACK 02 AUDIO
+    INDEX 00 00:00:00
+    INDEX 01 00:02:00"#;
+        let mut tracklist = Tracklist::parse(source).unwrap();
+        tracklist.files[0].tracks[0].duration = Some(Time::new(3, 0, 0));
+        let parsed = Tracklist::parse_embedded(&tracklist.to_embedded_cuesheet()).unwrap();
+        assert_eq!(parsed.files.len(), 1);
+        assert_eq!(parsed.files[0].name, "one.wav");
+        assert_eq!(
+            parsed.files[0].tracks[1].index,
+            vec![(0, Time::new(3, 0, 0)), (1, Time::new(3, 2, 0))]
+        );
+    }
+
+    #[test]
+    fn start_sample() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let tracks = &tracklist.files[0].tracks;
+        // 05:50:10 is 26260 frames of 588 samples each.
+        assert_eq!(tracks[1].start_sample(44100), Some(15_440_880));
+        assert_eq!(tracks[0].start_sample(44100), Some(0));
+
+        let mut track = tracks[0].clone();
+        track.index = vec![(1, Time::from_frames(-75))];
+        assert_eq!(track.start_sample(44100), None);
+    }
+
+    #[test]
+    fn pregap_violations() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        assert!(tracklist.pregap_violations().is_empty());
+
+        let source = r#"FILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 01 04:00:00
+  TRACK 03 AUDIO
+    INDEX 00 03:58:00
+    INDEX 01 08:00:00"#;
+        let tracklist = Tracklist::parse(source).unwrap();
+        assert_eq!(tracklist.pregap_violations(), vec![3]);
+    }
+
+    #[test]
+    fn short_tracks() {
+        let source = r#"FILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 01 04:00:00
+  TRACK 03 AUDIO
+    INDEX 01 04:00:30
+  TRACK 04 AUDIO
+    INDEX 01 08:00:00"#;
+        let tracklist = Tracklist::parse(source).unwrap();
+        assert_eq!(tracklist.short_tracks(Time::new(0, 2, 0)), vec![2]);
+        assert!(tracklist.short_tracks(Time::new(0, 0, 0)).is_empty());
+    }
+
+    #[test]
+    fn keyword_aliases() {
+        let source = r#"AUDIOFILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00"#;
+        assert!(Tracklist::parse_strict(source).is_err());
+
+        let mut options = ParseOptions::default();
+        options
+            .keyword_aliases
+            .insert("AudioFile".to_string(), "FILE".to_string());
+        let tracklist = Tracklist::parse_with_options(source, &options).unwrap();
+        assert_eq!(tracklist.files[0].name, "disc.wav");
+        assert_eq!(tracklist.files[0].tracks.len(), 1);
+    }
+
+    #[test]
+    fn title_for_track() {
+        let mut tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let track = &tracklist.files[0].tracks[0];
+        assert_eq!(tracklist.title_for_track(track), Some("Lady Nina"));
+
+        tracklist.files[0].tracks[0].title = None;
+        let track = &tracklist.files[0].tracks[0];
+        assert_eq!(tracklist.title_for_track(track), None);
+    }
+
+    #[test]
+    fn track_one_ok() {
+        assert!(Tracklist::parse(SAMPLE).unwrap().track_one_ok());
+
+        let source = r#"FILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 00 00:00:00
+    INDEX 01 00:32:00"#;
+        assert!(Tracklist::parse(source).unwrap().track_one_ok());
+
+        let late = source.replace("INDEX 00 00:00:00", "INDEX 00 00:10:00");
+        assert!(!Tracklist::parse(&late).unwrap().track_one_ok());
+
+        let missing = source.replace("    INDEX 01 00:32:00", "");
+        assert!(!Tracklist::parse(&missing).unwrap().track_one_ok());
+    }
+
+    #[test]
+    fn uncovered_regions() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let file = &tracklist.files[0];
+        let regions = file.uncovered_regions(Time::new(60, 0, 0));
+        // Only the six pregaps are uncovered.
+        assert_eq!(regions.len(), 6);
+        assert_eq!(regions[0], (Time::new(5, 47, 50), Time::new(5, 50, 10)));
+
+        let source = r#"FILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:02:00
+  TRACK 02 AUDIO
+    INDEX 00 04:00:00
+    INDEX 01 04:02:00"#;
+        let mut tracklist = Tracklist::parse(source).unwrap();
+        tracklist.files[0].tracks[1].duration = Some(Time::new(1, 0, 0));
+        assert_eq!(
+            tracklist.files[0].uncovered_regions(Time::new(6, 0, 0)),
+            vec![
+                (Time::new(0, 0, 0), Time::new(0, 2, 0)),
+                (Time::new(4, 0, 0), Time::new(4, 2, 0)),
+                (Time::new(5, 2, 0), Time::new(6, 0, 0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn omit_invalid_catalog() {
+        let mut tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let options = EmitOptions {
+            omit_invalid_catalog: true,
+            ..Default::default()
+        };
+        let cue = tracklist.to_cue_string_with_options(&options);
+        assert!(cue.contains("CATALOG 0724349703629\n"));
+
+        tracklist.catalog = Some("0724349703628".to_string());
+        let cue = tracklist.to_cue_string();
+        assert!(cue.contains("CATALOG 0724349703628\n"));
+        let cue = tracklist.to_cue_string_with_options(&options);
+        assert!(!cue.contains("CATALOG"));
+    }
+
+    #[test]
+    fn to_toc() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let toc = tracklist.to_toc();
+        assert!(toc.starts_with("CD_DA\nCATALOG \"0724349703629\"\n"));
+
+        let expected = r#"
+TRACK AUDIO
+ISRC "GBAYE9801905"
+FILE "Marillion - Misplaced Childhood (CD2).flac" 05:47:50 04:08:10
+START 00:02:35
+"#;
+        assert!(toc.contains(expected));
+        assert!(toc.contains("\nINDEX 01:38:45\n"));
+
+        let mut tracklist = tracklist;
+        tracklist.files[0].name = r#"C:\Music\"disc".flac"#.to_string();
+        tracklist.files[0].tracks[0].flags = vec![TrackFlag::Dcp, TrackFlag::FourChannel];
+        let toc = tracklist.to_toc();
+        let expected = r#"
+TRACK AUDIO
+COPY
+NO PRE_EMPHASIS
+FOUR_CHANNEL_AUDIO
+ISRC "GBAYE9801904"
+FILE "C:\\Music\\\"disc\".flac" 00:00:00"#;
+        assert!(toc.contains(expected));
+
+        let parsed = Tracklist::parse_toc(&toc).unwrap();
+        assert_eq!(parsed.files[0].name, tracklist.files[0].name);
+        assert_eq!(
+            parsed.files[0].tracks[0].flags,
+            tracklist.files[0].tracks[0].flags
+        );
+        assert!(parsed.files[0].tracks[1].flags.is_empty());
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let emitted = tracklist.to_string();
+        assert!(emitted.contains("\n  TRACK 02 AUDIO\n    TITLE \"Freaks\"\n"));
+        assert_eq!(Tracklist::parse(&emitted).unwrap(), tracklist);
+    }
+
+    #[test]
+    fn parse_toc() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let toc = Tracklist::parse_toc(&tracklist.to_toc()).unwrap();
+        assert!(toc.warnings.is_empty());
+        assert_eq!(toc.catalog, tracklist.catalog);
+
+        let cue = Tracklist::parse(&toc.to_cue_string()).unwrap();
+        assert_eq!(cue.files.len(), 1);
+        assert_eq!(cue.files[0].name, tracklist.files[0].name);
+        for (track, original) in cue.files[0].tracks.iter().zip(&tracklist.files[0].tracks) {
+            assert_eq!(track.number, original.number);
+            assert_eq!(track.index, original.index);
+            assert_eq!(track.duration, original.duration);
+            assert_eq!(track.isrc, original.isrc);
+        }
+
+        let source = r#"CD_DA
+CD_TEXT {
+  LANGUAGE_MAP { 0 : EN }
+}
+// Hidden intro
+TRACK AUDIO
+COPY
+PREGAP 00:02:00
+FILE "disc.wav" 0 3:00:00
+TRACK AUDIO
+FILE "disc.wav" 03:00:00
+"#;
+        let toc = Tracklist::parse_toc(source).unwrap();
+        assert_eq!(toc.warnings.len(), 0);
+        let tracks = &toc.files[0].tracks;
+        assert_eq!(tracks[0].flags, vec![TrackFlag::Dcp]);
+        assert_eq!(
+            tracks[0].index,
+            vec![(0, Time::new(0, 0, 0)), (1, Time::new(0, 0, 0))]
+        );
+        assert_eq!(tracks[0].duration, Some(Time::new(3, 0, 0)));
+        assert_eq!(tracks[1].index, vec![(1, Time::new(3, 0, 0))]);
+    }
+
+    #[test]
+    fn write_to() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let mut out = Vec::new();
+        tracklist.write_to(&mut out).unwrap();
+        assert_eq!(out, tracklist.to_string().into_bytes());
+    }
+
+    #[test]
+    fn leadout_msf() {
+        let mut tracklist = Tracklist::parse(SAMPLE).unwrap();
+        assert_eq!(tracklist.leadout_msf(), None);
+
+        tracklist.files[0].tracks[16].duration = Some(Time::new(4, 0, 0));
+        // 59:09:50 plus four minutes and the two seconds lead-in.
+        assert_eq!(tracklist.leadout_msf(), Some(Time::new(63, 11, 50)));
+    }
+
+    #[test]
+    fn track_date() {
+        let source = SAMPLE.replace(
+            "    TITLE \"Freaks\"\n",
+            "    TITLE \"Freaks\"\n    REM DATE 1984\n",
+        );
+        let tracklist = Tracklist::parse(&source).unwrap();
+        let tracks = &tracklist.files[0].tracks;
+        assert_eq!(tracks.len(), 17);
+        assert_eq!(tracks[1].date, Some("1984".to_string()));
+        assert_eq!(tracks[0].date, None);
+
+        let tags = tracks[1].as_tags();
+        assert_eq!(tags["DATE"], "1984");
+        assert_eq!(tags["TITLE"], "Freaks");
+        assert_eq!(tags["TRACKNUMBER"], "2");
+        assert!(!tracks[0].as_tags().contains_key("DATE"));
+
+        assert_eq!(Tracklist::parse(&tracklist.to_string()).unwrap(), tracklist);
+    }
+
+    #[test]
+    fn normalize_times() {
+        let source = r#"FILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 01 03:70:99"#;
+        let options = ParseOptions {
+            frames_per_second: 100,
+            ..Default::default()
+        };
+        let mut tracklist = Tracklist::parse_with_options(source, &options).unwrap();
+        assert_eq!(tracklist.invalid_frames(), vec![(2, 1)]);
+
+        tracklist.normalize_times();
+        assert!(tracklist.invalid_frames().is_empty());
+        assert_eq!(
+            tracklist.files[0].tracks[1].index[0],
+            (1, Time::new(4, 11, 24))
+        );
+    }
+
+    #[test]
+    fn crlf_line_ending() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let options = EmitOptions {
+            line_ending: LineEnding::Crlf,
+            ..Default::default()
+        };
+        let cue = tracklist.to_cue_string_with_options(&options);
+        assert!(cue.starts_with("REM GENRE \"Progressive Rock\"\r\nREM DATE 1985\r\n"));
+        assert_eq!(cue.matches('\n').count(), cue.matches("\r\n").count());
+        assert_eq!(Tracklist::parse(&cue).unwrap(), tracklist);
+    }
+
+    #[test]
+    fn formats_used() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        assert_eq!(tracklist.formats_used(), vec![FileFormat::Wave]);
+
+        let source = r#"FILE "data.bin" BINARY
+  TRACK 01 MODE1/2352
+    INDEX 01 00:00:00
+FILE "one.wav" WAVE
+  TRACK 02 AUDIO
+    INDEX 01 00:00:00
+FILE "two.wav" WAVE
+  TRACK 03 AUDIO
+    INDEX 01 00:00:00"#;
+        let tracklist = Tracklist::parse(source).unwrap();
+        assert_eq!(
+            tracklist.formats_used(),
+            vec![FileFormat::Binary, FileFormat::Wave]
+        );
+    }
+
+    #[test]
+    fn pregap_on_first_track() {
+        let source = r#"FILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    PREGAP 00:02:00
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 01 03:00:00"#;
+        let tracklist = Tracklist::parse(source).unwrap();
+        let tracks = &tracklist.files[0].tracks;
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(
+            tracks[0].index,
+            vec![(0, Time::new(0, 0, 0)), (1, Time::new(0, 0, 0))]
+        );
+        assert_eq!(tracklist.warnings.len(), 1);
+        assert!(tracklist.warnings[0].contains("track 1"));
+    }
+
+    #[test]
+    fn fill_percent() {
+        let mut tracklist = Tracklist::parse(SAMPLE).unwrap();
+        assert_eq!(tracklist.fill_percent(74), None);
+
+        // The last track starts at 59:09:50, so the disc is 62:54:00 long.
+        tracklist.files[0].tracks[16].duration = Some(Time::new(3, 44, 25));
+        let percent = tracklist.fill_percent(74).unwrap();
+        assert!((percent - 85.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn decreasing_index_times() {
+        let source = r#"FILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 01 05:00:00
+  TRACK 03 AUDIO
+    INDEX 01 04:00:00
+  TRACK 04 AUDIO
+    INDEX 01 06:00:00"#;
+        let tracklist = Tracklist::parse(source).unwrap();
+        let tracks = &tracklist.files[0].tracks;
+        assert_eq!(tracks[0].duration, Some(Time::new(5, 0, 0)));
+        assert_eq!(tracks[1].duration, None);
+        assert_eq!(tracks[2].duration, Some(Time::new(2, 0, 0)));
+    }
+
+    #[test]
+    fn implicit_file_with_leadout() {
+        let source = r#"REM LEAD-OUT 12:00:00
+TITLE "Image"
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 01 04:00:00
+  TRACK 03 AUDIO
+    INDEX 01 09:30:00"#;
+        let tracklist = Tracklist::parse(source).unwrap();
+        assert_eq!(tracklist.leadout, Some(Time::new(12, 0, 0)));
+        assert_eq!(tracklist.warnings.len(), 1);
+        assert_eq!(tracklist.files.len(), 1);
+        let durations: Vec<_> = tracklist.files[0]
+            .tracks
+            .iter()
+            .map(|track| track.duration.clone())
+            .collect();
+        assert_eq!(
+            durations,
+            vec![
+                Some(Time::new(4, 0, 0)),
+                Some(Time::new(5, 30, 0)),
+                Some(Time::new(2, 30, 0)),
+            ]
+        );
+
+        let strict = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        match Tracklist::parse_with_options(source, &strict) {
+            Err(Error::UnexpectedCommand { line, command }) => {
+                assert_eq!(line, 3);
+                assert!(command.starts_with("Track(1"));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        let reparsed = Tracklist::parse(&tracklist.to_string()).unwrap();
+        assert_eq!(reparsed.leadout, tracklist.leadout);
+    }
+
+    #[test]
+    fn implicit_file_round_trip() {
+        let source = r#"TITLE "Image"
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 01 04:00:00"#;
+        let tracklist = Tracklist::parse(source).unwrap();
+        let output = tracklist.to_cue_string();
+        assert!(!output.contains("FILE"));
+        let reparsed = Tracklist::parse(&output).unwrap();
+        assert_eq!(reparsed.files, tracklist.files);
+        assert_eq!(reparsed.warnings, tracklist.warnings);
+    }
+
+    #[test]
+    fn implicit_file_writers() {
+        let source = r#"TITLE "Image"
+  TRACK 01 AUDIO
+    TITLE "One"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 00 03:58:00
+    INDEX 01 04:00:00
+    INDEX 02 05:00:00"#;
+        let tracklist = Tracklist::parse(source).unwrap();
+        assert_eq!(tracklist.to_m3u(), "#EXTM3U\n");
+
+        let toc = tracklist.to_toc();
+        assert!(!toc.contains("FILE"));
+        assert!(!toc.contains("START"));
+        assert_eq!(toc.matches("TRACK AUDIO").count(), 2);
+        assert_eq!(Tracklist::parse_toc(&toc).unwrap().tracks().count(), 2);
+
+        assert!(tracklist.validate_files(Path::new(".")).is_empty());
+
+        let names: Vec<_> = tracklist
+            .to_tag_sidecars()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["01 - One.wav", "02.wav"]);
+    }
+
+    #[test]
+    fn postgap() {
+        let source = SAMPLE.replace(
+            "    INDEX 01 05:50:10\n",
+            "    INDEX 01 05:50:10\n    POSTGAP 00:02:00\n",
+        );
+        let tracklist = Tracklist::parse(&source).unwrap();
+        let tracks = &tracklist.files[0].tracks;
+        assert_eq!(tracks.len(), 17);
+        assert_eq!(tracks[1].postgap, Some(Time::new(0, 2, 0)));
+        assert_eq!(tracks[0].postgap, None);
+        assert_eq!(Tracklist::parse(&tracklist.to_string()).unwrap(), tracklist);
+    }
+
+    #[test]
+    fn songwriter() {
+        let source = SAMPLE
+            .replace(
+                "PERFORMER \"Marillion\"\nTITLE",
+                "PERFORMER \"Marillion\"\nSONGWRITER \"Kelly, Fish\"\nTITLE",
+            )
+            .replace(
+                "    TITLE \"Freaks\"\n",
+                "    TITLE \"Freaks\"\n    SONGWRITER Fish\n",
+            );
+        let tracklist = Tracklist::parse(&source).unwrap();
+        assert_eq!(tracklist.songwriter, Some("Kelly, Fish".to_string()));
+        let tracks = &tracklist.files[0].tracks;
+        assert_eq!(tracks.len(), 17);
+        assert_eq!(tracks[1].songwriter, Some("Fish".to_string()));
+        assert_eq!(tracks[0].songwriter, None);
+        assert_eq!(Tracklist::parse(&tracklist.to_string()).unwrap(), tracklist);
+    }
+
+    #[test]
+    fn to_tag_sidecars() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let sidecars = tracklist.to_tag_sidecars();
+        assert_eq!(sidecars.len(), 17);
+
+        let (ref name, ref tags) = sidecars[0];
+        assert_eq!(name, "01 - Lady Nina.flac");
+        assert_eq!(tags["ALBUM"], "Misplaced Childhood (CD2: Demo)");
+        assert_eq!(tags["TITLE"], "Lady Nina");
+        assert_eq!(tags["DATE"], "1985");
+        assert_eq!(tags["DISCNUMBER"], "2");
+    }
+
+    #[test]
+    fn flags() {
+        let source = SAMPLE.replace(
+            "    ISRC GBAYE9801905\n",
+            "    FLAGS DCP 4CH\n    ISRC GBAYE9801905\n",
+        );
+        let tracklist = Tracklist::parse(&source).unwrap();
+        let tracks = &tracklist.files[0].tracks;
+        assert_eq!(tracks.len(), 17);
+        assert_eq!(
+            tracks[1].flags,
+            vec![TrackFlag::Dcp, TrackFlag::FourChannel]
+        );
+        assert!(tracks[0].flags.is_empty());
+        assert_eq!(Tracklist::parse(&tracklist.to_string()).unwrap(), tracklist);
+
+        let source = source.replace("FLAGS DCP 4CH", "FLAGS PRE XYZ");
+        let tracklist = Tracklist::parse(&source).unwrap();
+        assert_eq!(
+            tracklist.files[0].tracks[1].flags,
+            vec![TrackFlag::Pre, TrackFlag::Other("XYZ".to_string())]
+        );
+    }
+
+    #[test]
+    fn tracks_without_isrc() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        assert!(tracklist.tracks_without_isrc().is_empty());
+
+        let source = SAMPLE
+            .replace("    ISRC GBAYE9801905\n", "")
+            .replace("    ISRC GBAYE9801920\n", "");
+        let tracklist = Tracklist::parse(&source).unwrap();
+        assert_eq!(tracklist.tracks_without_isrc(), vec![2, 17]);
+    }
+
+    #[test]
+    fn cdtextfile() {
+        let source = SAMPLE.replace(
+            "CATALOG 0724349703629\n",
+            "CATALOG 0724349703629\nCDTEXTFILE \"Misplaced Childhood.cdt\"\n",
+        );
+        let tracklist = Tracklist::parse(&source).unwrap();
+        assert_eq!(
+            tracklist.cdtextfile,
+            Some("Misplaced Childhood.cdt".to_string())
+        );
+        assert_eq!(tracklist.files[0].tracks.len(), 17);
+        assert_eq!(Tracklist::parse(&tracklist.to_string()).unwrap(), tracklist);
+
+        let source = SAMPLE.replace(
+            "CATALOG 0724349703629\n",
+            "CATALOG 0724349703629\nCDTEXTFILE disc.cdt\n",
+        );
+        let tracklist = Tracklist::parse(&source).unwrap();
+        assert_eq!(tracklist.cdtextfile, Some("disc.cdt".to_string()));
+    }
+
+    #[test]
+    fn parse_strict() {
+        assert!(Tracklist::parse_strict(SAMPLE).is_ok());
+
+        let source = SAMPLE.replace("  TRACK 03 AUDIO\n", "  BOGUS 1\n  TRACK 03 AUDIO\n");
+        let tracklist = Tracklist::parse(&source).unwrap();
+        assert_eq!(tracklist.files[0].tracks.len(), 17);
+        assert_eq!(tracklist.warnings.len(), 1);
+        let error = Tracklist::parse_strict(&source).unwrap_err().to_string();
+        assert!(error.contains("line 22"));
+        assert!(error.contains("BOGUS"));
+
+        let source = SAMPLE.replace(
+            "  TRACK 03 AUDIO\n",
+            "CDTEXTFILE disc.cdt\n  TRACK 03 AUDIO\n",
+        );
+        let tracklist = Tracklist::parse(&source).unwrap();
+        assert_eq!(tracklist.files[0].tracks.len(), 2);
+        assert_eq!(tracklist.warnings.len(), 1);
+        let error = Tracklist::parse_strict(&source).unwrap_err().to_string();
+        assert!(error.contains("line 22"));
+        assert!(error.contains("Cdtextfile"));
+    }
+
+    #[test]
+    fn with_leadin() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let shifted = tracklist.with_leadin().unwrap();
+        let tracks = &shifted.files[0].tracks;
+        assert_eq!(tracks[0].index, vec![(1, Time::new(0, 2, 0))]);
+        assert_eq!(
+            tracks[1].index,
+            vec![(0, Time::new(5, 49, 50)), (1, Time::new(5, 52, 10))]
+        );
+        assert_eq!(tracks[0].duration, tracklist.files[0].tracks[0].duration);
+
+        let source = r#"REM LEAD-OUT 04:00:00
+FILE "one.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 01 03:00:00
+FILE "two.wav" WAVE
+  TRACK 03 AUDIO
+    INDEX 00 00:00:00
+    INDEX 01 00:02:00"#;
+        let mut tracklist = Tracklist::parse(source).unwrap();
+        assert_eq!(tracklist.with_leadin(), None);
+
+        tracklist.files[0].tracks[1].duration = Some(Time::new(2, 30, 0));
+        let shifted = tracklist.with_leadin().unwrap();
+        let starts: Vec<_> = shifted.tracks().map(|track| track.index.clone()).collect();
+        assert_eq!(
+            starts,
+            vec![
+                vec![(1, Time::new(0, 2, 0))],
+                vec![(1, Time::new(3, 2, 0))],
+                vec![(0, Time::new(5, 32, 0)), (1, Time::new(5, 34, 0))],
+            ]
+        );
+        assert_eq!(shifted.leadout, Some(Time::new(9, 32, 0)));
+    }
+
+    #[test]
+    fn unknown_rems() {
+        let source = SAMPLE.replace(
+            "REM DATE 1985",
+            "REM DATE 1985\nREM encoder \"foobar\"\nREM REPLAYGAIN_ALBUM_GAIN -7.45 dB",
+        );
+        let tracklist = Tracklist::parse(&source).unwrap();
+        assert_eq!(tracklist.rem["ENCODER"], "foobar");
+        assert_eq!(tracklist.rem["REPLAYGAIN_ALBUM_GAIN"], "-7.45 dB");
+        assert_eq!(tracklist.rem.len(), 2);
+        assert_eq!(tracklist.date, Some("1985".to_string()));
+    }
+
+    #[test]
+    fn set_total_length() {
+        let mut tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let file = &mut tracklist.files[0];
+        assert!(file.set_total_length(Time::new(59, 0, 0)).is_err());
+        assert_eq!(file.tracks[16].duration, None);
+
+        file.set_total_length(Time::new(63, 10, 0)).unwrap();
+        assert_eq!(file.tracks[16].duration, Some(Time::new(4, 0, 25)));
+    }
+
+    #[test]
+    fn validate() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        assert_eq!(tracklist.validate(), Ok(()));
+        let report = tracklist.validation_report();
+        assert!(report.is_ok());
+        assert_eq!(report.warnings.len(), 1);
+        assert!(report.warnings[0].contains("last track"));
+
+        let source = r#"FILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    ISRC GBAYE9801904
+    INDEX 01 00:00:00
+  TRACK 03 AUDIO
+    ISRC GBAYE9801904
+    INDEX 01 04:00:00
+    INDEX 02 03:00:00
+  TRACK 04 AUDIO
+    INDEX 00 03:30:00"#;
+        let report = Tracklist::parse(source).unwrap().validation_report();
+        assert!(!report.is_ok());
+        assert_eq!(report.errors.len(), 4);
+        assert!(report.warnings.iter().any(|w| w.contains("same ISRC")));
+
+        let mut tracklist = Tracklist::parse(SAMPLE).unwrap();
+        tracklist.files[0].tracks[1].isrc = Some("gbaye9801904".to_string());
+        let report = tracklist.validation_report();
+        assert!(report.is_ok());
+        assert!(report
+            .warnings
+            .contains(&"Tracks 1 and 2 have the same ISRC GBAYE9801904.".to_string()));
+    }
+
+    #[test]
+    fn from_file() {
+        use std::{env, process};
+
+        let path = env::temp_dir().join(format!("cue_sheet_from_file_{}.cue", process::id()));
+        fs::write(&path, SAMPLE).unwrap();
+        let tracklist = Tracklist::from_file(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(tracklist.unwrap(), Tracklist::parse(SAMPLE).unwrap());
+        assert!(Tracklist::from_file(&path).is_err());
+    }
+
+    #[test]
+    fn total_duration() {
+        let source = r#"FILE "a.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 01 03:10:50
+FILE "b.wav" WAVE
+  TRACK 03 AUDIO
+    INDEX 01 00:00:00"#;
+        let mut tracklist = Tracklist::parse(source).unwrap();
+        assert_eq!(tracklist.total_duration(), None);
+
+        tracklist.files[0].tracks[1].duration = Some(Time::new(2, 0, 30));
+        tracklist.files[1].tracks[0].duration = Some(Time::new(4, 59, 0));
+        assert_eq!(tracklist.total_duration(), Some(Time::new(10, 10, 5)));
+    }
+
+    #[test]
+    fn track_start() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let tracks = &tracklist.files[0].tracks;
+        assert_eq!(tracks[1].start(), Some(Time::new(5, 50, 10)));
+
+        let source = r#"FILE "a.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 00 00:00:00"#;
+        let tracklist = Tracklist::parse(source).unwrap();
+        assert_eq!(tracklist.files[0].tracks[0].start(), None);
+    }
+
+    #[test]
+    fn track_end() {
+        let source = r#"FILE "a.wav" WAVE
+  TRACK 01 AUDIO
     INDEX 01 00:00:00
   TRACK 02 AUDIO
-    TITLE "Freaks"
-    PERFORMER "Marillion"
-    ISRC GBAYE9801905
-    INDEX 00 05:47:50
-    INDEX 01 05:50:10
+    INDEX 01 03:59:70
   TRACK 03 AUDIO
-    TITLE "Kayleigh (Alternate Mix)"
-    PERFORMER "Marillion"
-    ISRC GBAYE9801906
-    INDEX 00 09:55:60
-    INDEX 01 09:58:20
+    INDEX 01 05:00:05"#;
+        let tracklist = Tracklist::parse(source).unwrap();
+        let tracks = &tracklist.files[0].tracks;
+        // 03:59:70 + 01:00:10 carries over both frames and seconds.
+        assert_eq!(tracks[1].duration, Some(Time::new(1, 0, 10)));
+        assert_eq!(tracks[1].end(), Some(Time::new(5, 0, 5)));
+        assert_eq!(tracks[1].end(), tracks[2].start());
+        assert_eq!(tracks[2].end(), None);
+    }
+
+    #[test]
+    fn tracks() {
+        let mut tracklist = Tracklist::parse(SAMPLE).unwrap();
+        assert_eq!(tracklist.tracks().count(), 17);
+        assert_eq!(tracklist.tracks().last().unwrap().number, 17);
+
+        for track in tracklist.tracks_mut() {
+            track.title = None;
+        }
+        assert!(tracklist.tracks().all(|track| track.title.is_none()));
+    }
+
+    #[test]
+    fn split_points() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let points = tracklist.files[0].split_points();
+        assert_eq!(points.len(), 17);
+        assert_eq!(
+            points[0],
+            (1, Time::new(0, 0, 0), Some(Time::new(5, 50, 10)))
+        );
+        assert_eq!(
+            points[1],
+            (2, Time::new(5, 50, 10), Some(Time::new(9, 58, 20)))
+        );
+        assert_eq!(points[16].0, 17);
+        assert_eq!(points[16].2, None);
+    }
+
+    #[test]
+    fn to_m3u() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let m3u = tracklist.to_m3u();
+        let lines: Vec<&str> = m3u.lines().collect();
+        assert_eq!(lines.len(), 1 + 2 * 17);
+        assert_eq!(lines[0], "#EXTM3U");
+        assert_eq!(lines[1], "#EXTINF:348,Marillion - Lady Nina");
+        assert_eq!(lines[2], "Marillion - Misplaced Childhood (CD2).flac");
+        assert_eq!(lines[3], "#EXTINF:246,Marillion - Freaks");
+        assert!(lines[33].starts_with("#EXTINF:-1,Marillion - "));
+    }
+
+    #[test]
+    fn to_ffmetadata() {
+        let mut tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let metadata = tracklist.to_ffmetadata();
+        assert!(metadata.starts_with(";FFMETADATA1\ntitle=Misplaced Childhood (CD2: Demo)\n"));
+        assert_eq!(metadata.matches("[CHAPTER]").count(), 17);
+
+        // 05:50:10 is 350 * 75 + 10 frames, 09:58:20 is 598 * 75 + 20 frames.
+        let first = "[CHAPTER]\nTIMEBASE=1/75\nSTART=0\nEND=26260\ntitle=Lady Nina\n";
+        let second = "[CHAPTER]\nTIMEBASE=1/75\nSTART=26260\nEND=44870\ntitle=Freaks\n";
+        assert!(metadata.contains(first));
+        assert!(metadata.contains(second));
+        assert_eq!(metadata.matches("END=").count(), 16);
+
+        tracklist.files[0]
+            .set_total_length(Time::new(70, 0, 0))
+            .unwrap();
+        assert!(tracklist.to_ffmetadata().contains("END=315000\n"));
+
+        let source = r#"REM LEADOUT 03:00:00
+FILE "a.wav" WAVE
+  TRACK 01 AUDIO
+    TITLE "One"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Two"
+    INDEX 01 02:00:00
+FILE "b.wav" WAVE
+  TRACK 03 AUDIO
+    TITLE "Three"
+    INDEX 01 00:00:00"#;
+        let mut tracklist = Tracklist::parse(source).unwrap();
+        tracklist.files[0]
+            .set_total_length(Time::new(4, 0, 0))
+            .unwrap();
+        let metadata = tracklist.to_ffmetadata();
+        // The lead-out is relative to the last file, which starts at 04:00:00 on the disc.
+        assert!(metadata.contains("START=18000\nEND=31500\ntitle=Three\n"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        extern crate serde_json;
+
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let json = serde_json::to_string(&tracklist).unwrap();
+        assert!(json.contains(r#""duration":{"minutes":5,"seconds":47,"frames":50}"#));
+
+        let deserialized: Tracklist = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, tracklist);
+
+        let source = SAMPLE.replace("  TRACK 03 AUDIO\n", "  BOGUS 1\n  TRACK 03 AUDIO\n");
+        let tracklist = Tracklist::parse(&source).unwrap();
+        assert!(!tracklist.warnings.is_empty());
+        let json = serde_json::to_string(&tracklist).unwrap();
+        assert!(!json.contains("warnings"));
+        let deserialized: Tracklist = serde_json::from_str(&json).unwrap();
+        assert!(deserialized.warnings.is_empty());
+        assert_eq!(deserialized, tracklist);
+    }
+
+    #[test]
+    fn equality_ignores_warnings() {
+        let source = SAMPLE.replace("  TRACK 03 AUDIO\n", "  BOGUS 1\n  TRACK 03 AUDIO\n");
+        let lenient = Tracklist::parse(&source).unwrap();
+        let clean = Tracklist::parse(SAMPLE).unwrap();
+        assert_ne!(lenient.warnings, clean.warnings);
+        assert_eq!(lenient, clean);
+    }
+
+    #[test]
+    fn error_variants() {
+        let source = SAMPLE.replace("  TRACK 03 AUDIO\n", "  BOGUS 1\n  TRACK 03 AUDIO\n");
+        match Tracklist::parse_strict(&source) {
+            Err(Error::UnexpectedCommand { line, command }) => {
+                assert_eq!(line, 22);
+                assert_eq!(command, "\"BOGUS\"");
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let source = SAMPLE.replace("INDEX 01 05:50:10", "INDEX 01 05:50:80");
+        match Tracklist::parse(&source) {
+            Err(Error::Parse { line, message }) => {
+                assert_eq!(line, 21);
+                assert!(message.contains("05:50:80"));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn error_line_numbers() {
+        let source = SAMPLE.replace(
+            "    INDEX 00 09:55:60\n",
+            "    PREGAP 00:02:00\n    TITLE \"Gap\"\n",
+        );
+        let error = Tracklist::parse(&source).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 26: Pregap is not followed by an index!"
+        );
+
+        let source = SAMPLE.replace("    INDEX 01 05:50:10\n", "    INDEX 01 \"05:50:10\n");
+        match Tracklist::parse(&source) {
+            Err(Error::Parse { line, .. }) => assert_eq!(line, 21),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn validate_catalog() {
+        let source = SAMPLE.replace("CATALOG 0724349703629", "CATALOG 07243497036");
+        let tracklist = Tracklist::parse(&source).unwrap();
+        assert_eq!(
+            tracklist.validate(),
+            Err(vec![ValidationError::InvalidCatalog(
+                "07243497036".to_string()
+            )])
+        );
+    }
+
+    #[test]
+    fn validate_isrc() {
+        let mut tracklist = Tracklist::parse(SAMPLE).unwrap();
+        tracklist.files[0].tracks[2].isrc = Some("gbaye980190".to_string());
+        tracklist.files[0].tracks[3].isrc = Some("gbaye9801907".to_string());
+        assert_eq!(
+            tracklist.validate(),
+            Err(vec![ValidationError::InvalidIsrc {
+                track: 3,
+                isrc: "GBAYE980190".to_string(),
+            }])
+        );
+    }
+
+    #[test]
+    fn frames_out_of_range() {
+        let source = SAMPLE.replace("INDEX 00 05:47:50", "INDEX 00 05:47:80");
+        let error = Tracklist::parse(&source).unwrap_err();
+        assert!(error.to_string().contains("05:47:80"));
+
+        let toc = "CD_DA\nTRACK AUDIO\nFILE \"disc.wav\" 0 04:00:75\n";
+        assert!(Tracklist::parse_toc(toc).is_err());
+    }
+
+    #[test]
+    fn hidden_track_length() {
+        let source = r#"FILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 00 00:00:00
+    INDEX 01 01:12:40
+  TRACK 02 AUDIO
+    INDEX 00 04:58:00
+    INDEX 01 05:00:00"#;
+        let tracklist = Tracklist::parse(source).unwrap();
+        assert_eq!(
+            tracklist.files[0].hidden_track_length(),
+            Some(Time::new(1, 12, 40))
+        );
+
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        assert_eq!(tracklist.files[0].hidden_track_length(), None);
+    }
+
+    #[test]
+    fn validate_track_numbers() {
+        let source = r#"FILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 01 03:00:00
   TRACK 04 AUDIO
-    TITLE "Lavender Blue"
-    PERFORMER "Marillion"
-    ISRC GBAYE9801907
-    INDEX 00 13:57:60
-    INDEX 01 14:01:72
-  TRACK 05 AUDIO
-    TITLE "Heart of Lothian (Extended Mix)"
-    PERFORMER "Marillion"
-    ISRC GBAYE9801908
-    INDEX 00 18:23:15
-    INDEX 01 18:24:12
+    INDEX 01 06:00:00
   TRACK 06 AUDIO
-    TITLE "Pseudo Silk Kimono (Album Demo)"
-    PERFORMER "Marillion"
-    ISRC GBAYE9801909
-    INDEX 00 24:10:15
-    INDEX 01 24:18:17
-  TRACK 07 AUDIO
-    TITLE "Kayleigh (Album Demo)"
-    PERFORMER "Marillion"
-    ISRC GBAYE9801910
-    INDEX 01 26:29:70
-  TRACK 08 AUDIO
-    TITLE "Lavender (Album Demo)"
-    PERFORMER "Marillion"
-    ISRC GBAYE9801911
-    INDEX 01 30:36:20
-  TRACK 09 AUDIO
-    TITLE "Bitter Suite (I. Brief Encounter II. Lost Weekend) (Album Demo)"
-    PERFORMER "Marillion"
-    ISRC GBAYE9801912
-    INDEX 01 33:14:10
-    INDEX 02 34:52:55
-  TRACK 10 AUDIO
-    TITLE "Lords of the Backstage (Album Demo)"
-    PERFORMER "Marillion"
-    ISRC GBAYE9801913
-    INDEX 01 36:08:70
-  TRACK 11 AUDIO
-    TITLE "Blue Angel (Album Demo)"
-    PERFORMER "Marillion"
-    ISRC GBAYE9801914
-    INDEX 01 37:55:50
-  TRACK 12 AUDIO
-    TITLE "Misplaced Rendezvous (Album Demo)"
-    PERFORMER "Marillion"
-    ISRC GBAYE9801915
-    INDEX 01 39:42:17
-    INDEX 02 41:01:57
-  TRACK 13 AUDIO
-    TITLE "Heart of Lothian (I. Wide Boy II. Curtain Call) (Album Demo)"
-    PERFORMER "Marillion"
-    ISRC GBAYE9801916
-    INDEX 01 41:38:57
-    INDEX 02 44:26:35
-  TRACK 14 AUDIO
-    TITLE "Waterhole (Expresso Bongo) (Album Demo)"
-    PERFORMER "Marillion"
-    ISRC GBAYE9801917
-    INDEX 00 45:27:70
-    INDEX 01 45:28:15
-  TRACK 15 AUDIO
-    TITLE "Passing Strangers (I. Mylo II. Perimeter Walk III. Threshold) (Album Demo)"
-    PERFORMER "Marillion"
-    ISRC GBAYE9801918
-    INDEX 01 47:28:62
-    INDEX 02 49:40:52
-    INDEX 03 51:28:62
-    INDEX 04 53:45:72
-  TRACK 16 AUDIO
-    TITLE "Childhoods End? (Album Demo)"
-    PERFORMER "Marillion"
-    ISRC GBAYE9801919
-    INDEX 01 56:45:67
-  TRACK 17 AUDIO
-    TITLE "White Feather (Album Demo)"
-    PERFORMER "Marillion"
-    ISRC GBAYE9801920
-    INDEX 01 59:09:50"#;
-
+    INDEX 01 09:00:00"#;
         let tracklist = Tracklist::parse(source).unwrap();
-        assert_eq!(tracklist.genre.unwrap(), "Progressive Rock".to_string());
-        assert_eq!(tracklist.date.unwrap(), "1985".to_string());
-        assert_eq!(tracklist.discid.unwrap(), "DC0E6811".to_string());
-        assert_eq!(tracklist.comment.unwrap(), "ExactAudioCopy v0.95b3".to_string());
-        assert_eq!(tracklist.discnumber.unwrap(), 2);
-        assert_eq!(tracklist.totaldiscs.unwrap(), 2);
-        assert_eq!(tracklist.catalog.unwrap(), "0724349703629".to_string());
-        assert_eq!(tracklist.performer.unwrap(), "Marillion".to_string());
-        assert_eq!(tracklist.title.unwrap(), "Misplaced Childhood (CD2: Demo)".to_string());
+        assert_eq!(
+            tracklist.validate(),
+            Err(vec![ValidationError::NonSequentialTrack {
+                expected: 3,
+                found: 4,
+            }])
+        );
+    }
 
-        let files = tracklist.files;
-        assert_eq!(files.len(), 1);
+    #[test]
+    fn renumber() {
+        let mut tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let extra = tracklist.files[0].tracks.split_off(10);
+        tracklist.files.push(TrackFile {
+            tracks: extra,
+            name: "second.flac".to_string(),
+            format: FileFormat::Wave,
+        });
+        for (i, track) in tracklist.tracks_mut().enumerate() {
+            track.number = (i as u32 * 7) % 17 + 3;
+        }
+        assert!(tracklist.validate().is_err());
 
-        let ref f = files[0];
-        assert_eq!(f.name, "Marillion - Misplaced Childhood (CD2).flac".to_string());
-        assert_eq!(f.format, FileFormat::Wave);
+        tracklist.renumber();
+        let numbers: Vec<u32> = tracklist.tracks().map(|track| track.number).collect();
+        assert_eq!(numbers, (1..=17).collect::<Vec<u32>>());
+        assert_eq!(tracklist.files[1].tracks[0].number, 11);
+    }
 
-        let ref tracks = f.tracks;
-        assert_eq!(tracks.len(), 17);
+    #[test]
+    fn track_by_isrc() {
+        let tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let track = tracklist.track_by_isrc("GBAYE9801906").unwrap();
+        assert_eq!(track.number, 3);
+        assert_eq!(track.title, Some("Kayleigh (Alternate Mix)".to_string()));
+        assert_eq!(tracklist.track_by_isrc("gbaye9801906").unwrap().number, 3);
+        assert!(tracklist.track_by_isrc("GBAYE9899999").is_none());
+    }
 
-        assert_eq!(tracks[0].number, 1);
-        assert_eq!(tracks[0].track_type, TrackType::Audio);
-        assert_eq!(tracks[0].title, Some("Lady Nina".to_string()));
-        assert_eq!(tracks[0].performer, Some("Marillion".to_string()));
-        assert_eq!(tracks[0].isrc, Some("GBAYE9801904".to_string()));
-        assert_eq!(tracks[0].index[0], (1, Time::new(0, 0, 0)));
-        assert_eq!(tracks[0].duration, Some(Time::new(5, 47, 50)));
+    #[test]
+    fn escaped_quotes() {
+        let source = SAMPLE.replace("TITLE \"Freaks\"", r#"TITLE "She said \"hi\"""#);
+        let tracklist = Tracklist::parse(&source).unwrap();
+        let title = "She said \"hi\"";
+        assert_eq!(tracklist.files[0].tracks[1].title, Some(title.to_string()));
 
-        assert_eq!(tracks[1].number, 2);
-        assert_eq!(tracks[1].track_type, TrackType::Audio);
-        assert_eq!(tracks[1].title, Some("Freaks".to_string()));
-        assert_eq!(tracks[1].performer, Some("Marillion".to_string()));
-        assert_eq!(tracks[1].isrc, Some("GBAYE9801905".to_string()));
-        assert_eq!(tracks[1].index[0], (0, Time::new(5, 47, 50)));
-        assert_eq!(tracks[1].index[1], (1, Time::new(5, 50, 10)));
-        assert_eq!(tracks[1].duration, Some(Time::new(4, 5, 50)));
+        let cue = tracklist.to_cue_string();
+        assert!(cue.contains(r#"    TITLE "She said \"hi\"""#));
+        let reparsed = Tracklist::parse(&cue).unwrap();
+        assert_eq!(reparsed.files[0].tracks[1].title, Some(title.to_string()));
+        assert_eq!(reparsed.files, tracklist.files);
+    }
 
-        assert_eq!(tracks[14].number, 15);
-        assert_eq!(tracks[14].track_type, TrackType::Audio);
-        assert_eq!(tracks[14].title, Some("Passing Strangers (I. Mylo II. Perimeter Walk III. Threshold) (Album Demo)".to_string()));
-        assert_eq!(tracks[14].performer, Some("Marillion".to_string()));
-        assert_eq!(tracks[14].isrc, Some("GBAYE9801918".to_string()));
-        assert_eq!(tracks[14].index[0], (1, Time::new(47, 28, 62)));
-        assert_eq!(tracks[14].index[1], (2, Time::new(49, 40, 52)));
-        assert_eq!(tracks[14].index[2], (3, Time::new(51, 28, 62)));
-        assert_eq!(tracks[14].index[3], (4, Time::new(53, 45, 72)));
-        assert_eq!(tracks[14].duration, Some(Time::new(9, 17, 5)));
+    #[test]
+    fn parse_toc_unbalanced_braces() {
+        let source = "CD_DA\nCD_TEXT { } }\nTRACK AUDIO\nFILE \"disc.wav\" 0\n";
+        match Tracklist::parse_toc(source) {
+            Err(Error::Parse { line, .. }) => assert_eq!(line, 2),
+            other => panic!("unexpected result {:?}", other),
+        }
 
-        assert_eq!(tracks[15].number, 16);
-        assert_eq!(tracks[15].track_type, TrackType::Audio);
-        assert_eq!(tracks[15].title, Some("Childhoods End? (Album Demo)".to_string()));
-        assert_eq!(tracks[15].performer, Some("Marillion".to_string()));
-        assert_eq!(tracks[15].isrc, Some("GBAYE9801919".to_string()));
-        assert_eq!(tracks[15].index[0], (1, Time::new(56, 45, 67)));
-        assert_eq!(tracks[15].duration, Some(Time::new(2, 23, 58)));
+        let source = "CD_DA\n}\nTRACK AUDIO\nFILE \"disc.wav\" 0\n";
+        assert!(Tracklist::parse_toc(source).is_err());
     }
 
     #[test]
-    fn pregap() {
-        let src = r#"FILE "disc.img" BINARY
-                       TRACK 01 MODE1/2352
-                         INDEX 01 00:00:00
-                       TRACK 02 AUDIO
-                         PREGAP 00:02:00
-                         INDEX 01 58:41:36
-                       TRACK 03 AUDIO
-                         INDEX 00 61:06:08
-                         INDEX 01 61:08:08"#;
+    fn parse_toc_error_lines() {
+        let cases = [
+            ("CD_DA\nTRACK AUDIO\nFILE \"disc.wav\" 0x:00:00\n", 3),
+            (
+                "CD_DA\nTRACK AUDIO\nFILE \"disc.wav\" 0\nSTART 00:61:00\n",
+                4,
+            ),
+            ("CD_DA\n\nTRACK BOGUS\n", 3),
+        ];
+        for &(source, expected) in cases.iter() {
+            match Tracklist::parse_toc(source) {
+                Err(Error::Parse { line, .. }) => assert_eq!(line, expected),
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+    }
 
-        let tracklist = Tracklist::parse(src).unwrap();
+    #[test]
+    fn parse_toc_flags() {
+        let source = r#"CD_DA
+TRACK AUDIO
+COPY
+PRE_EMPHASIS
+FOUR_CHANNEL_AUDIO
+FILE "disc.wav" 0
+TRACK AUDIO
+NO COPY
+NO PRE_EMPHASIS
+TWO_CHANNEL_AUDIO
+FILE "disc.wav" 03:00:00
+"#;
+        let toc = Tracklist::parse_toc(source).unwrap();
+        assert!(toc.warnings.is_empty());
+        let tracks = &toc.files[0].tracks;
+        assert_eq!(
+            tracks[0].flags,
+            vec![TrackFlag::Dcp, TrackFlag::Pre, TrackFlag::FourChannel]
+        );
+        assert!(tracks[1].flags.is_empty());
+    }
 
-        let ref f = tracklist.files[0];
-        let ref tracks = f.tracks;
+    #[test]
+    fn escaped_backslashes() {
+        let mut tracklist = Tracklist::parse(SAMPLE).unwrap();
+        let titles = [r"Path C:\", r#"Mixed \" and "quoted" C:\Music"#];
+        tracklist.files[0].tracks[0].title = Some(titles[0].to_string());
+        tracklist.files[0].tracks[1].title = Some(titles[1].to_string());
+        tracklist.comment = Some(titles[1].to_string());
 
-        assert_eq!(tracks[0].index[0], (1, Time::new(0, 0, 0)));
-        assert_eq!(tracks[1].index[0], (0, Time::new(58, 39, 36)));
-        assert_eq!(tracks[1].index[1], (1, Time::new(58, 41, 36)));
-        assert_eq!(tracks[2].index[0], (0, Time::new(61, 06, 08)));
-        assert_eq!(tracks[2].index[1], (1, Time::new(61, 08, 08)));
+        let cue = tracklist.to_cue_string();
+        assert!(cue.contains(r#"    TITLE "Path C:\""#));
+        let reparsed = Tracklist::parse(&cue).unwrap();
+        assert_eq!(
+            reparsed.files[0].tracks[0].title,
+            Some(titles[0].to_string())
+        );
+        assert_eq!(
+            reparsed.files[0].tracks[1].title,
+            Some(titles[1].to_string())
+        );
+        assert_eq!(reparsed.comment, tracklist.comment);
+
+        let source = SAMPLE.replace(
+            "FILE \"Marillion - Misplaced Childhood (CD2).flac\"",
+            r#"FILE "C:\Music\" "#,
+        );
+        let tracklist = Tracklist::parse(&source).unwrap();
+        assert_eq!(tracklist.files[0].name, r"C:\Music\");
+    }
+
+    #[test]
+    fn unc_path_round_trip() {
+        let source = SAMPLE.replace(
+            "FILE \"Marillion - Misplaced Childhood (CD2).flac\"",
+            r#"FILE "\\nas\music\a.wav""#,
+        );
+        let tracklist = Tracklist::parse(&source).unwrap();
+        assert_eq!(tracklist.files[0].name, r"\\nas\music\a.wav");
+        let cue = tracklist.to_cue_string();
+        assert!(cue.contains(r#"FILE "\\nas\music\a.wav" WAVE"#));
+        assert_eq!(Tracklist::parse(&cue).unwrap(), tracklist);
+    }
+
+    #[test]
+    fn warning_line_numbers() {
+        let source = r#"ISRC GBAYE9801904
+FILE "disc.wav" WAVE
+  TRACK 01 AUDIO
+    CATALOG 0724349703629
+    INDEX 01 00:00:00"#;
+        let tracklist = Tracklist::parse(source).unwrap();
+        assert_eq!(
+            tracklist.warnings,
+            vec![
+                "line 1: Ignoring ISRC outside of a track.".to_string(),
+                "line 4: Ignoring CATALOG inside of track 1.".to_string(),
+            ]
+        );
     }
 }
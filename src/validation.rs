@@ -0,0 +1,220 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Quality checks of tracklists.
+
+use std::collections::HashMap;
+use std::fmt;
+use tracklist::Tracklist;
+
+/// A problem which makes a tracklist unusable for burning or splitting.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ValidationError {
+    /// The track numbers don't count up from 1 without gaps, only the first violation is
+    /// reported.
+    ///
+    /// Numbering continues across files. Gaps in the numbering are harmless for many uses, so
+    /// callers may want to treat this one as a warning.
+    NonSequentialTrack {
+        /// The track number expected at this position.
+        expected: u32,
+        /// The track number found instead.
+        found: u32,
+    },
+
+    /// The track with the given number has no `INDEX 01`.
+    MissingIndex01(u32),
+
+    /// An index of a track lies before the index preceding it.
+    IndexOrder {
+        /// Number of the track.
+        track: u32,
+        /// Number of the index going backwards.
+        index: u32,
+    },
+
+    /// An index has 75 or more frames.
+    FramesOutOfRange {
+        /// Number of the track.
+        track: u32,
+        /// Number of the index.
+        index: u32,
+    },
+
+    /// A track starts before the last index of the previous track of the same file.
+    Overlap {
+        /// Number of the overlapping track.
+        track: u32,
+        /// Number of the track before it.
+        previous: u32,
+    },
+
+    /// The catalog number isn't a 13 digit UPC/EAN.
+    InvalidCatalog(String),
+
+    /// The ISRC of a track isn't of the form `CCXXXYYNNNNN`.
+    InvalidIsrc {
+        /// Number of the track.
+        track: u32,
+        /// The ISRC, in uppercase.
+        isrc: String,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValidationError::NonSequentialTrack { expected, found } => {
+                write!(f, "Expected track {} but found track {}.", expected, found)
+            }
+            ValidationError::MissingIndex01(track) => {
+                write!(f, "Track {} has no INDEX 01.", track)
+            }
+            ValidationError::IndexOrder { track, index } => write!(
+                f,
+                "INDEX {:02} of track {} is before the previous index.",
+                index, track
+            ),
+            ValidationError::FramesOutOfRange { track, index } => write!(
+                f,
+                "INDEX {:02} of track {} has 75 or more frames.",
+                index, track
+            ),
+            ValidationError::Overlap { track, previous } => {
+                write!(f, "Track {} overlaps track {}.", track, previous)
+            }
+            ValidationError::InvalidCatalog(ref catalog) => {
+                write!(f, "CATALOG {:?} is not 13 digits long.", catalog)
+            }
+            ValidationError::InvalidIsrc { track, ref isrc } => {
+                write!(f, "ISRC {:?} of track {} is malformed.", isrc, track)
+            }
+        }
+    }
+}
+
+/// Problems found by `Tracklist::validation_report`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ValidationReport {
+    /// Problems which make the tracklist unusable for burning or splitting.
+    pub errors: Vec<ValidationError>,
+
+    /// Things worth noting which don't make the tracklist invalid.
+    pub warnings: Vec<String>,
+}
+
+impl ValidationReport {
+    /// Returns true if no errors were found, warnings are allowed.
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+pub(crate) fn validate(tracklist: &Tracklist) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let tracks: Vec<_> = tracklist.tracks().collect();
+
+    if let Some(ref catalog) = tracklist.catalog {
+        if catalog.len() != 13 || !catalog.chars().all(|c| c.is_ascii_digit()) {
+            report
+                .errors
+                .push(ValidationError::InvalidCatalog(catalog.clone()));
+        }
+    }
+
+    let mut numbers = (1..).zip(tracks.iter().map(|track| track.number));
+    if let Some((expected, found)) = numbers.find(|&(expected, found)| expected != found) {
+        report
+            .errors
+            .push(ValidationError::NonSequentialTrack { expected, found });
+    }
+
+    let mut isrcs = HashMap::new();
+    for track in &tracks {
+        if let Some(ref isrc) = track.isrc {
+            let isrc = isrc.to_uppercase();
+            if !is_valid_isrc(&isrc) {
+                report.errors.push(ValidationError::InvalidIsrc {
+                    track: track.number,
+                    isrc: isrc.clone(),
+                });
+            }
+            if let Some(first) = isrcs.insert(isrc.clone(), track.number) {
+                report.warnings.push(format!(
+                    "Tracks {} and {} have the same ISRC {}.",
+                    first, track.number, isrc
+                ));
+            }
+        }
+        if track.index_time(1).is_none() {
+            report
+                .errors
+                .push(ValidationError::MissingIndex01(track.number));
+        }
+        for pair in track.index.windows(2) {
+            if pair[1].1 < pair[0].1 {
+                report.errors.push(ValidationError::IndexOrder {
+                    track: track.number,
+                    index: pair[1].0,
+                });
+            }
+        }
+    }
+
+    for (track, index) in tracklist.invalid_frames() {
+        report
+            .errors
+            .push(ValidationError::FramesOutOfRange { track, index });
+    }
+
+    for file in &tracklist.files {
+        for pair in file.tracks.windows(2) {
+            let end = pair[0].index.iter().map(|index| &index.1).max();
+            let next = pair[1].index.first().map(|index| &index.1);
+            if let (Some(end), Some(next)) = (end, next) {
+                if next < end {
+                    report.errors.push(ValidationError::Overlap {
+                        track: pair[1].number,
+                        previous: pair[0].number,
+                    });
+                }
+            }
+        }
+    }
+
+    if let Some(track) = tracks.last() {
+        if track.duration.is_none() {
+            report.warnings.push(format!(
+                "The duration of the last track {} is unknown.",
+                track.number
+            ));
+        }
+    }
+
+    report
+}
+
+/// Checks that `isrc` has the form `CCXXXYYNNNNN`: a country code of two letters, a registrant
+/// code of three letters or digits, two digits of the year and a designation code of five digits.
+///
+/// Letters may be lowercase.
+fn is_valid_isrc(isrc: &str) -> bool {
+    let bytes = isrc.as_bytes();
+    bytes.len() == 12
+        && bytes[..2].iter().all(u8::is_ascii_alphabetic)
+        && bytes[2..5].iter().all(u8::is_ascii_alphanumeric)
+        && bytes[5..].iter().all(u8::is_ascii_digit)
+}